@@ -14,15 +14,53 @@ struct CubeMaterial {
     metallic: f32,
     reflectance: f32,
     colors: array<vec4<f32>, 7>,
+    patterns: array<vec4<u32>, 7>,
+    picture_faces: u32,
+    center_markers: u32,
+    half_size: f32,
+    sticker_border: f32,
+    sticker_radius: f32,
+    cube_sides: u32,
+    sub_cube_spacing: f32,
 };
 
 @group(1) @binding(0)
 var<uniform> material: CubeMaterial;
+@group(1) @binding(1)
+var back_picture: texture_2d<f32>;
+@group(1) @binding(2)
+var back_sampler: sampler;
+@group(1) @binding(3)
+var down_picture: texture_2d<f32>;
+@group(1) @binding(4)
+var down_sampler: sampler;
+@group(1) @binding(5)
+var left_picture: texture_2d<f32>;
+@group(1) @binding(6)
+var left_sampler: sampler;
+@group(1) @binding(7)
+var right_picture: texture_2d<f32>;
+@group(1) @binding(8)
+var right_sampler: sampler;
+@group(1) @binding(9)
+var up_picture: texture_2d<f32>;
+@group(1) @binding(10)
+var up_sampler: sampler;
+@group(1) @binding(11)
+var front_picture: texture_2d<f32>;
+@group(1) @binding(12)
+var front_sampler: sampler;
 
 struct Vertex {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) uv: vec2<f32>,
+    // transform of the sub cube relative to the cube
+    @location(8) transform_0: vec4<f32>,
+    @location(9) transform_1: vec4<f32>,
+    @location(10) transform_2: vec4<f32>,
+    @location(11) transform_3: vec4<f32>,
+    @location(12) outer_faces: u32,
 };
 
 struct VertexOutput {
@@ -31,20 +69,37 @@ struct VertexOutput {
     @location(4) position: vec3<f32>,
     @location(5) normal: vec3<f32>,
     @location(6) uv: vec2<f32>,
+    @location(7) picture_point: vec3<f32>,
+    @location(8) @interpolate(flat) outer_faces: u32,
 };
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
 
-    var model = mesh.model;
+    let transform = mat4x4<f32>(
+        vertex.transform_0,
+        vertex.transform_1,
+        vertex.transform_2,
+        vertex.transform_3,
+    );
+    var model = mesh.model * transform;
 
-    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+    out.world_normal = mesh_normal_local_to_world((transform * vec4<f32>(vertex.normal, 0.0)).xyz);
     out.world_position = mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
     out.clip_position = mesh_position_world_to_clip(out.world_position);
     out.position = vertex.position;
     out.normal = vertex.normal;
     out.uv = vertex.uv;
+    out.outer_faces = vertex.outer_faces;
+
+    // the sub cube is only rotated around the center of the cube, so turning
+    // its center back by its own rotation gives its solved place
+    let basis = mat3x3<f32>(transform[0].xyz, transform[1].xyz, transform[2].xyz);
+    let home = transpose(basis) * transform[3].xyz;
+    // -0.5 to 0.5 over the whole cube in the solved orientation
+    out.picture_point = (home / material.sub_cube_spacing
+        + vertex.position / (2.0 * material.half_size)) / f32(material.cube_sides);
 
     return out;
 }
@@ -56,16 +111,143 @@ struct FragmentInput {
     @location(4) position: vec3<f32>,
     @location(5) normal: vec3<f32>,
     @location(6) uv: vec2<f32>,
+    @location(7) picture_point: vec3<f32>,
+    @location(8) @interpolate(flat) outer_faces: u32,
 };
 
+// whether the point of the sticker, -1 to 1 on both axes, is on the pattern
+fn on_pattern(pattern: u32, p: vec2<f32>) -> bool {
+    switch (pattern) {
+        // stripes
+        case 1u: {
+            return fract((p.x + p.y) * 2.0) < 0.35;
+        }
+        // dots
+        case 2u: {
+            return length(fract(p * 1.5 + 0.25) - 0.5) < 0.2;
+        }
+        // ring
+        case 3u: {
+            return abs(length(p) - 0.4) < 0.1;
+        }
+        // cross
+        case 4u: {
+            return min(abs(p.x), abs(p.y)) < 0.1 && max(abs(p.x), abs(p.y)) < 0.5;
+        }
+        // square
+        case 5u: {
+            return max(abs(p.x), abs(p.y)) < 0.3;
+        }
+        // triangle
+        case 6u: {
+            return p.y > -0.3 && abs(p.x) < (0.45 - p.y) * 0.6;
+        }
+        default: {
+            return false;
+        }
+    }
+}
+
+// signed distance to the sticker, a rounded square inset from the edges of
+// the face, in the -1 to 1 coordinates of the face
+fn sticker_distance(p: vec2<f32>) -> f32 {
+    let half_extent = 1.0 - 2.0 * material.sticker_border;
+    let radius = min(2.0 * material.sticker_radius, half_extent);
+    let q = abs(p) - vec2<f32>(half_extent - radius);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+// texture coordinates of the point of the cube on the face with the normal,
+// the pictures are seen from outside with the up face on top and the front
+// face below the up face
+fn picture_uv(normal: vec3<f32>, cube_point: vec3<f32>) -> vec2<f32> {
+    var u = vec3<f32>(-1.0, 0.0, 0.0);
+    var v = vec3<f32>(0.0, -1.0, 0.0);
+    if (normal.x > 0.5) {
+        u = vec3<f32>(0.0, 0.0, -1.0);
+    } else if (normal.x < -0.5) {
+        u = vec3<f32>(0.0, 0.0, 1.0);
+    } else if (normal.y > 0.5) {
+        u = vec3<f32>(1.0, 0.0, 0.0);
+        v = vec3<f32>(0.0, 0.0, 1.0);
+    } else if (normal.y < -0.5) {
+        u = vec3<f32>(1.0, 0.0, 0.0);
+        v = vec3<f32>(0.0, 0.0, -1.0);
+    } else if (normal.z > 0.5) {
+        u = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    return vec2<f32>(dot(cube_point, u), dot(cube_point, v)) + 0.5;
+}
+
+// the face is only known per fragment, so the level is explicit
+fn picture_color(index: i32, uv: vec2<f32>) -> vec4<f32> {
+    switch (index) {
+        case 0: {
+            return textureSampleLevel(back_picture, back_sampler, uv, 0.0);
+        }
+        case 1: {
+            return textureSampleLevel(down_picture, down_sampler, uv, 0.0);
+        }
+        case 2: {
+            return textureSampleLevel(left_picture, left_sampler, uv, 0.0);
+        }
+        case 4: {
+            return textureSampleLevel(right_picture, right_sampler, uv, 0.0);
+        }
+        case 5: {
+            return textureSampleLevel(up_picture, up_sampler, uv, 0.0);
+        }
+        default: {
+            return textureSampleLevel(front_picture, front_sampler, uv, 0.0);
+        }
+    }
+}
+
 @fragment
 fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
     var normal2 = in.normal + 1.0;
     var index = i32(dot(normal2, vec3<f32>(1.0, 2.0, 3.0)) - 3.0);
-    var output_color = material.colors[index];
-    if ((in.position.x * in.position.x + in.position.y * in.position.y + in.position.z * in.position.z) > 0.006) {
-      output_color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    // the inner faces of the sub cubes are black
+    let outer = (in.outer_faces & (1u << u32(index))) != 0u;
+    var output_color = select(vec4<f32>(0.0, 0.0, 0.0, 1.0), material.colors[index], outer);
+
+    // the sticker plane of the face, -1 to 1 from edge to edge
+    var p = in.position.xy;
+    if (abs(in.normal.x) > 0.5) {
+        p = in.position.zy;
+    } else if (abs(in.normal.y) > 0.5) {
+        p = in.position.xz;
+    }
+    p = p / material.half_size;
+    // derivatives need uniform control flow, so the edge is smoothed here
+    let distance = sticker_distance(p);
+    let edge = fwidth(distance);
+    let coverage = 1.0 - smoothstep(-edge, edge, distance);
+    if (outer && (material.picture_faces & (1u << u32(index))) != 0u) {
+        output_color = picture_color(index, picture_uv(in.normal, in.picture_point));
+    } else if (outer && on_pattern(material.patterns[index].x, p)) {
+        // dark marks on light colors and light ones on dark colors
+        let luminance = dot(output_color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        output_color = vec4<f32>(vec3<f32>(select(0.9, 0.05, luminance > 0.3)), 1.0);
+    }
+    // only centers have a single outer face
+    if (material.center_markers != 0u && outer && countOneBits(in.outer_faces) == 1u) {
+        // a bar at the edge of the sticker that is up in the solved cube,
+        // the up and down faces have the back and the front edge up
+        var up = vec3<f32>(0.0, 1.0, 0.0);
+        if (abs(in.normal.y) > 0.5) {
+            up = vec3<f32>(0.0, 0.0, -in.normal.y);
+        }
+        let side = cross(in.normal, up);
+        let along_up = dot(in.position, up) / material.half_size;
+        let along_side = dot(in.position, side) / material.half_size;
+        if (along_up > 0.5 && along_up < 0.75 && abs(along_side) < 0.3) {
+            let luminance = dot(output_color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+            output_color = vec4<f32>(vec3<f32>(select(0.9, 0.05, luminance > 0.3)), 1.0);
+        }
     }
+    // the black body shows around the sticker
+    output_color = mix(vec4<f32>(0.0, 0.0, 0.0, 1.0), output_color, coverage);
 
     let flags: u32 = 0u;
     var pbr_input: PbrInput;
@@ -0,0 +1,38 @@
+#import bevy_pbr::mesh_view_types
+#import bevy_pbr::mesh_types
+
+@group(0) @binding(0)
+var<uniform> view: View;
+
+@group(1) @binding(0)
+var<uniform> mesh: Mesh;
+
+// NOTE: Bindings must come before functions that use them!
+#import bevy_pbr::mesh_functions
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    // transform of the sub cube relative to the cube
+    @location(8) transform_0: vec4<f32>,
+    @location(9) transform_1: vec4<f32>,
+    @location(10) transform_2: vec4<f32>,
+    @location(11) transform_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let transform = mat4x4<f32>(
+        vertex.transform_0,
+        vertex.transform_1,
+        vertex.transform_2,
+        vertex.transform_3,
+    );
+
+    var out: VertexOutput;
+    out.clip_position = mesh_position_local_to_clip(mesh.model * transform, vec4<f32>(vertex.position, 1.0));
+    return out;
+}
@@ -0,0 +1,240 @@
+use std::{fmt::Display, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color_scheme::ColorScheme,
+    cube_material::CubeMaterial,
+    rubiks_cube::RubiksCube,
+    rubiks_cube_plugin::{MoveQueue, SubCubeMaterial, TurnCommitted, TurnSource},
+    solve_timer::format_duration,
+    GameStates,
+};
+
+pub struct BlindfoldedPlugin;
+
+impl Plugin for BlindfoldedPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Blindfolded::default());
+
+        app.add_system_set(
+            SystemSet::on_update(GameStates::InGame)
+                .with_system(update_blind_solve)
+                .with_system(blindfold_stickers.after(update_blind_solve)),
+        );
+        app.add_system_set(SystemSet::on_exit(GameStates::InGame).with_system(reset_blind_solve));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlindResult {
+    pub memo: Duration,
+    pub execution: Duration,
+    // pieces not solved when the player gave up, a DNF unless it is zero
+    pub pieces_off: usize,
+}
+
+impl BlindResult {
+    pub fn time(&self) -> Duration {
+        self.memo + self.execution
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.pieces_off == 0
+    }
+}
+
+impl Display for BlindResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_solved() {
+            write!(f, "{}", format_duration(self.time()))
+        } else {
+            write!(
+                f,
+                "DNF({}), {} pieces off",
+                format_duration(self.time()),
+                self.pieces_off
+            )
+        }
+    }
+}
+
+/// Phases of a blindfolded solve. The timer runs from the end of the
+/// scramble, the stickers are hidden during the execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlindPhase {
+    // no scrambled cube to solve
+    #[default]
+    Idle,
+    Memo(Duration),
+    Execution {
+        memo: Duration,
+        execution: Duration,
+    },
+    Finished(BlindResult),
+}
+
+impl BlindPhase {
+    pub fn start_memo(&mut self) {
+        *self = BlindPhase::Memo(Duration::ZERO);
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        match self {
+            BlindPhase::Memo(memo) => *memo += delta,
+            BlindPhase::Execution { execution, .. } => *execution += delta,
+            BlindPhase::Idle | BlindPhase::Finished(_) => {}
+        }
+    }
+
+    pub fn start_execution(&mut self) {
+        if let BlindPhase::Memo(memo) = *self {
+            *self = BlindPhase::Execution {
+                memo,
+                execution: Duration::ZERO,
+            };
+        }
+    }
+
+    /// Ends the execution, either solved or given up with pieces left.
+    pub fn finish(&mut self, pieces_off: usize) {
+        if let BlindPhase::Execution { memo, execution } = *self {
+            *self = BlindPhase::Finished(BlindResult {
+                memo,
+                execution,
+                pieces_off,
+            });
+        }
+    }
+
+    pub fn stickers_hidden(&self) -> bool {
+        matches!(self, BlindPhase::Execution { .. })
+    }
+}
+
+impl Display for BlindPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlindPhase::Idle => write!(f, "Scramble to start"),
+            BlindPhase::Memo(memo) => write!(f, "Memo: {}", format_duration(*memo)),
+            BlindPhase::Execution { memo, execution } => {
+                write!(f, "{}", format_duration(*memo + *execution))
+            }
+            BlindPhase::Finished(result) => write!(f, "{result}"),
+        }
+    }
+}
+
+/// Blindfolded mode of the game, it replaces the inspection and the
+/// solve timer while enabled.
+#[derive(Resource, Debug, Default)]
+pub struct Blindfolded {
+    pub enabled: bool,
+    pub phase: BlindPhase,
+}
+
+fn update_blind_solve(
+    time: Res<Time>,
+    move_queue: Res<MoveQueue>,
+    rubiks_cube: Query<&RubiksCube>,
+    mut turn_events: EventReader<TurnCommitted>,
+    mut blindfolded: ResMut<Blindfolded>,
+    mut scrambling: Local<bool>,
+) {
+    let Ok(rb) = rubiks_cube.get_single() else {
+        return;
+    };
+    if !blindfolded.enabled {
+        blindfolded.phase = BlindPhase::Idle;
+        turn_events.clear();
+        return;
+    }
+    let phase = &mut blindfolded.phase;
+    phase.tick(time.delta());
+    let mut turned = false;
+    for TurnCommitted(queued) in turn_events.iter() {
+        match queued.source {
+            TurnSource::Scramble => {
+                *phase = BlindPhase::Idle;
+                *scrambling = true;
+            }
+            TurnSource::Player | TurnSource::Undo | TurnSource::Redo => turned = true,
+            TurnSource::Replay => {}
+        }
+    }
+    // the memo starts once the whole scramble is shown
+    if *scrambling && move_queue.is_empty() {
+        phase.start_memo();
+        *scrambling = false;
+    }
+    if turned && rb.is_solved() {
+        phase.finish(0);
+    }
+}
+
+fn blindfold_stickers(
+    blindfolded: Res<Blindfolded>,
+    color_scheme: Res<ColorScheme>,
+    sub_cube_material: Option<Res<SubCubeMaterial>>,
+    mut materials: ResMut<Assets<CubeMaterial>>,
+    mut hidden: Local<bool>,
+) {
+    let Some(sub_cube_material) = sub_cube_material else {
+        return;
+    };
+    // a new cube is spawned with its colors
+    if sub_cube_material.is_added() {
+        *hidden = false;
+    }
+    let hide = blindfolded.phase.stickers_hidden();
+    if hide != *hidden {
+        sub_cube_material.paint(hide, &color_scheme, &mut materials);
+        *hidden = hide;
+    }
+}
+
+pub fn reset_blind_solve(mut blindfolded: ResMut<Blindfolded>) {
+    blindfolded.phase = BlindPhase::Idle;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn blind_phases() {
+        let mut phase = BlindPhase::default();
+        phase.tick(secs(1));
+        phase.start_execution();
+        assert_eq!(phase, BlindPhase::Idle);
+
+        phase.start_memo();
+        phase.tick(secs(20));
+        assert!(!phase.stickers_hidden());
+        // giving up is only possible once the stickers are hidden
+        phase.finish(3);
+        assert_eq!(phase, BlindPhase::Memo(secs(20)));
+
+        phase.start_execution();
+        assert!(phase.stickers_hidden());
+        phase.tick(secs(40));
+        phase.finish(0);
+        let BlindPhase::Finished(result) = phase else {
+            panic!("{phase:?} is not finished");
+        };
+        assert_eq!(result.time(), secs(60));
+        assert_eq!(result.to_string(), "1:00.00");
+        assert!(!phase.stickers_hidden());
+
+        let result = BlindResult {
+            pieces_off: 3,
+            ..result
+        };
+        assert_eq!(result.to_string(), "DNF(1:00.00), 3 pieces off");
+    }
+}
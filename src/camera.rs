@@ -3,28 +3,19 @@ use bevy::{
     prelude::*,
 };
 
-use crate::GameStates;
+use crate::{
+    input_bindings::{Action, ActionInput},
+    GameStates,
+};
 
 pub struct CameraControllerPlugin;
 
 impl Plugin for CameraControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(OrbitCameraKeys::default());
-
         app.add_system_set(SystemSet::on_update(GameStates::InGame).with_system(pan_orbit_camera));
-    }
-}
-
-#[derive(Resource)]
-pub struct OrbitCameraKeys {
-    orbit_button: MouseButton,
-}
-
-impl Default for OrbitCameraKeys {
-    fn default() -> Self {
-        Self {
-            orbit_button: MouseButton::Right,
-        }
+        app.add_system_set(SystemSet::on_update(GameStates::Replay).with_system(pan_orbit_camera));
+        app.add_system_set(SystemSet::on_update(GameStates::Trainer).with_system(pan_orbit_camera));
+        app.add_system_set(SystemSet::on_update(GameStates::Fmc).with_system(pan_orbit_camera));
     }
 }
 
@@ -45,8 +36,7 @@ impl Default for OrbitCamera {
 
 fn pan_orbit_camera(
     windows: Res<Windows>,
-    input_mouse: Res<Input<MouseButton>>,
-    orbit_camera_keys: Res<OrbitCameraKeys>,
+    action_input: ActionInput,
     mut ev_motion: EventReader<MouseMotion>,
     mut ev_scroll: EventReader<MouseWheel>,
     mut query: Query<(&mut OrbitCamera, &mut Transform)>,
@@ -54,7 +44,7 @@ fn pan_orbit_camera(
     let mut rotation_move = Vec2::ZERO;
     let mut scroll = 0.0;
 
-    if input_mouse.pressed(orbit_camera_keys.orbit_button) {
+    if action_input.pressed(Action::OrbitCamera) {
         for ev in ev_motion.iter() {
             rotation_move += ev.delta;
         }
@@ -0,0 +1,271 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    notation::{moves_layer_turns, parse_moves, Move},
+    rubiks_cube::{LayerTurn, RubiksCube, FACE_NORMALS},
+};
+
+// shipped in the assets folder and built into the binary, the tests check
+// every algorithm of it
+const CASES: &str = include_str!("../assets/cases.ron");
+
+/// Adjustments of the last layer before or after an algorithm.
+pub const AUFS: [&str; 4] = ["", "U", "U2", "U'"];
+
+// whole cube rotations that bring each face to the top
+const TOP_ROTATIONS: [&str; 6] = ["", "x", "x'", "x2", "z", "z'"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CaseKind {
+    F2l,
+    Oll,
+    Pll,
+}
+
+impl CaseKind {
+    pub const ALL: [CaseKind; 3] = [CaseKind::F2l, CaseKind::Oll, CaseKind::Pll];
+}
+
+impl Display for CaseKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaseKind::F2l => write!(f, "F2L"),
+            CaseKind::Oll => write!(f, "OLL"),
+            CaseKind::Pll => write!(f, "PLL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Case {
+    pub kind: CaseKind,
+    pub name: String,
+    // solves the case with the last layer on top, F2L cases insert
+    // the front right pair
+    pub algorithm: String,
+}
+
+impl Case {
+    /// Turns that set up the case on a solved cube, `pre_auf` is done before
+    /// the algorithm and `post_auf` after it.
+    pub fn setup_turns(&self, pre_auf: usize, post_auf: usize) -> Vec<LayerTurn> {
+        let layer_turns = |moves: &str| moves_layer_turns(&parse_moves(moves).unwrap(), 3);
+        [AUFS[post_auf], &self.algorithm, AUFS[pre_auf]]
+            .iter()
+            .flat_map(|moves| layer_turns(moves).into_iter().rev())
+            .map(LayerTurn::inverse)
+            .collect()
+    }
+
+    /// Cubes showing the case for every AUF before the algorithm and, for
+    /// PLLs, also after it. The AUF before the algorithm is the index modulo 4.
+    pub fn states(&self) -> Vec<RubiksCube> {
+        let post_aufs = match self.kind {
+            CaseKind::F2l | CaseKind::Oll => 1,
+            CaseKind::Pll => AUFS.len(),
+        };
+        let mut states = Vec::new();
+        for post_auf in 0..post_aufs {
+            for pre_auf in 0..AUFS.len() {
+                let mut rb = RubiksCube::new(3);
+                for turn in self.setup_turns(pre_auf, post_auf) {
+                    rb.turn(&turn);
+                }
+                states.push(rb);
+            }
+        }
+        states
+    }
+}
+
+/// Case database of the trainer and the last layer recognition.
+#[derive(Resource, Debug, Clone)]
+pub struct Cases(pub Vec<Case>);
+
+impl Cases {
+    pub fn load() -> Self {
+        Self(ron::from_str(CASES).expect("the case database is checked by the tests"))
+    }
+
+    pub fn of_kind(&self, kind: CaseKind) -> impl Iterator<Item = (usize, &Case)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(move |(_, case)| case.kind == kind)
+    }
+}
+
+impl Default for Cases {
+    fn default() -> Self {
+        Self::load()
+    }
+}
+
+/// The 3x3x3 turned so that each face is on top, with the rotation used.
+pub fn top_views(rb: &RubiksCube) -> impl Iterator<Item = (Vec<Move>, RubiksCube)> + '_ {
+    TOP_ROTATIONS.iter().map(|rotation| {
+        let rotation = parse_moves(rotation).unwrap();
+        let mut rb = rb.clone();
+        for turn in moves_layer_turns(&rotation, 3) {
+            rb.turn(&turn);
+        }
+        (rotation, rb)
+    })
+}
+
+// every piece below the top layer is solved
+pub fn f2l_solved(rb: &RubiksCube) -> bool {
+    (-1..=0).all(|y| (-1..=1).all(|x| (-1..=1).all(|z| rb.piece_solved(IVec3::new(x, y, z)))))
+}
+
+/// Whether the step trained by cases of the kind is done, with any face on top.
+pub fn step_solved(rb: &RubiksCube, kind: CaseKind) -> bool {
+    top_views(rb).any(|(_, rb)| {
+        f2l_solved(&rb)
+            && match kind {
+                CaseKind::F2l => true,
+                CaseKind::Oll => rb.face_oriented(IVec3::Y),
+                CaseKind::Pll => AUFS.iter().any(|auf| {
+                    let mut rb = rb.clone();
+                    for turn in moves_layer_turns(&parse_moves(auf).unwrap(), 3) {
+                        rb.turn(&turn);
+                    }
+                    rb.is_solved()
+                }),
+            }
+    })
+}
+
+/// Colors of the top layer stickers as the normals of the centers with the
+/// same color, edges and corners in a fixed order.
+pub fn last_layer_colors(rb: &RubiksCube) -> Vec<IVec3> {
+    let mut colors = Vec::new();
+    for x in -1..=1 {
+        for z in -1..=1 {
+            let pos = rb.offset_pos(IVec3::new(x, 1, z));
+            for normal in [IVec3::Y, IVec3::X * x, IVec3::Z * z] {
+                if normal == IVec3::ZERO {
+                    continue;
+                }
+                let sticker = rb.sticker(pos, normal);
+                let center = FACE_NORMALS
+                    .into_iter()
+                    .find(|center| rb.center_sticker(*center) == sticker);
+                colors.extend(center);
+            }
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    // with the states of the cases of the kind, where the pattern tells cases apart
+    fn check_cases<T: Eq + std::hash::Hash>(
+        kind: CaseKind,
+        count: usize,
+        valid: impl Fn(&RubiksCube) -> bool,
+        pattern: impl Fn(&RubiksCube) -> T,
+    ) {
+        let cases = Cases::load();
+        assert_eq!(cases.of_kind(kind).count(), count);
+        let mut patterns = HashSet::new();
+        for (_, case) in cases.of_kind(kind) {
+            let states = case.states();
+            for rb in states.iter() {
+                assert!(valid(rb), "{} is not a {kind} case", case.name);
+            }
+            let case_patterns: HashSet<T> = states.iter().map(&pattern).collect();
+            assert!(
+                patterns.is_disjoint(&case_patterns),
+                "{} is a duplicate",
+                case.name
+            );
+            patterns.extend(case_patterns);
+        }
+    }
+
+    #[test]
+    fn f2l_cases() {
+        let pair = [IVec3::new(1, -1, 1), IVec3::new(1, 0, 1)];
+        check_cases(
+            CaseKind::F2l,
+            41,
+            |rb| {
+                let others_solved = (-1..=0).all(|y| {
+                    (-1..=1).all(|x| {
+                        (-1..=1).all(|z| {
+                            let offset = IVec3::new(x, y, z);
+                            pair.contains(&offset) || rb.piece_solved(offset)
+                        })
+                    })
+                });
+                others_solved && !pair.iter().all(|offset| rb.piece_solved(*offset))
+            },
+            // stickers of the pair pieces wherever they are
+            |rb| {
+                let mut stickers = Vec::new();
+                for pos in 0..27 {
+                    let offset = rb.centered_coords(pos) / 2;
+                    for normal in FACE_NORMALS {
+                        if normal.dot(offset) != 1 {
+                            continue;
+                        }
+                        let sticker = rb.sticker(pos, normal);
+                        let home = rb.centered_coords(rb.pos_to_cube[pos as usize].1 as u32) / 2;
+                        let in_pair = pair.contains(&home);
+                        stickers.push(in_pair.then_some(sticker));
+                    }
+                }
+                stickers
+            },
+        );
+    }
+
+    #[test]
+    fn oll_cases() {
+        check_cases(
+            CaseKind::Oll,
+            57,
+            |rb| f2l_solved(rb) && !rb.face_oriented(IVec3::Y),
+            |rb| {
+                last_layer_colors(rb)
+                    .into_iter()
+                    .map(|color| color == IVec3::Y)
+                    .collect::<Vec<_>>()
+            },
+        );
+    }
+
+    #[test]
+    fn pll_cases() {
+        check_cases(
+            CaseKind::Pll,
+            21,
+            |rb| f2l_solved(rb) && rb.face_oriented(IVec3::Y) && !step_solved(rb, CaseKind::Pll),
+            last_layer_colors,
+        );
+    }
+
+    #[test]
+    fn step_solved_any_top() {
+        let mut rb = RubiksCube::new(3);
+        for turn in moves_layer_turns(&parse_moves("R U R' U' x z").unwrap(), 3) {
+            rb.turn(&turn);
+        }
+        assert!(!step_solved(&rb, CaseKind::F2l));
+        for turn in moves_layer_turns(&parse_moves("z' x' U R U' R' U'").unwrap(), 3) {
+            rb.turn(&turn);
+        }
+        assert!(step_solved(&rb, CaseKind::F2l));
+        assert!(step_solved(&rb, CaseKind::Oll));
+        assert!(step_solved(&rb, CaseKind::Pll));
+    }
+}
@@ -0,0 +1,269 @@
+use std::{fmt::Display, time::Duration};
+
+use bevy::prelude::IVec3;
+
+use crate::{
+    notation::moves_layer_turns,
+    replay::Replay,
+    rubiks_cube::{RubiksCube, FACE_NORMALS},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Cross,
+    // number of the inserted pair, 1 to 4
+    F2l(u8),
+    Oll,
+    Pll,
+}
+
+impl Stage {
+    // stages in the order they are solved
+    fn from_level(level: usize) -> Self {
+        match level {
+            1 => Stage::Cross,
+            2..=5 => Stage::F2l(level as u8 - 1),
+            6 => Stage::Oll,
+            _ => Stage::Pll,
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Cross => write!(f, "Cross"),
+            Stage::F2l(pair) => write!(f, "F2L {pair}"),
+            Stage::Oll => write!(f, "OLL"),
+            Stage::Pll => write!(f, "PLL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Split {
+    pub stage: Stage,
+    // time since the start of the solve when the stage was completed
+    pub time: Duration,
+    // time and moves spent on this stage only
+    pub duration: Duration,
+    pub moves: u32,
+    // moves since the start of the solve
+    pub moves_total: u32,
+}
+
+impl Split {
+    pub fn tps(&self) -> f32 {
+        if self.duration.is_zero() {
+            0.0
+        } else {
+            self.moves as f32 / self.duration.as_secs_f32()
+        }
+    }
+}
+
+// number of solved stages with the cross on the face with the normal
+fn level(rb: &RubiksCube, cross: IVec3) -> usize {
+    let sides: Vec<IVec3> = FACE_NORMALS
+        .iter()
+        .copied()
+        .filter(|normal| normal.dot(cross) == 0)
+        .collect();
+    let cross_solved = sides.iter().all(|side| rb.piece_solved(cross + *side));
+    if !cross_solved {
+        return 0;
+    }
+    // slots are the corner and edge between two neighbouring sides
+    let pairs = sides
+        .iter()
+        .flat_map(|a| sides.iter().map(move |b| (*a, *b)))
+        .filter(|(a, b)| a.dot(*b) == 0 && (a.x, a.y, a.z) < (b.x, b.y, b.z))
+        .filter(|(a, b)| rb.piece_solved(*a + *b) && rb.piece_solved(cross + *a + *b))
+        .count();
+    if pairs < 4 {
+        return 1 + pairs;
+    }
+    if !rb.face_oriented(-cross) {
+        return 5;
+    }
+    if !rb.is_solved() {
+        return 6;
+    }
+    7
+}
+
+/// Splits the solve of a 3x3x3 replay into the CFOP stages. The cross
+/// can be on any face, the first one completed is used. Stages completed
+/// with the same turn (e.g. an OLL skip) get splits without moves.
+pub fn analyze(replay: &Replay) -> Option<Vec<Split>> {
+    if replay.cube_size != 3 {
+        return None;
+    }
+    let mut rb = RubiksCube::new(3);
+    for turn in moves_layer_turns(&replay.scramble, 3) {
+        rb.turn(&turn);
+    }
+
+    let start = replay
+        .turns
+        .iter()
+        .find(|timed| !timed.turn.is_rotation(3))?
+        .time;
+    let mut cross = None;
+    let mut splits: Vec<Split> = Vec::new();
+    // moves in the half turn metric like `Move::htm`
+    let mut moves = 0;
+    let mut unpaired_turn = None;
+    // stages solved by the scramble are reached before the first turn
+    let states = std::iter::once(None).chain(replay.turns.iter().map(Some));
+    for timed in states {
+        let mut time = Duration::ZERO;
+        if let Some(timed) = timed {
+            rb.turn(&timed.turn);
+            let turn = timed.turn;
+            if unpaired_turn == Some(turn) {
+                // the second quarter of a half turn
+                unpaired_turn = None;
+            } else if !turn.is_rotation(3) {
+                // the middle slice counts as two outer layer turns
+                moves += if turn.layer == 1 && turn.width == 1 {
+                    2
+                } else {
+                    1
+                };
+                unpaired_turn = Some(turn);
+            }
+            time = timed.time.saturating_sub(start);
+        }
+        let cross = match cross {
+            Some(cross) => cross,
+            None => {
+                // when several crosses are solved at once the most advanced counts
+                let Some(normal) = FACE_NORMALS
+                    .iter()
+                    .copied()
+                    .filter(|normal| level(&rb, *normal) > 0)
+                    .max_by_key(|normal| level(&rb, *normal))
+                else {
+                    continue;
+                };
+                *cross.insert(normal)
+            }
+        };
+        // stages are reached only once, breaking a pair does not undo its split
+        for reached in splits.len() + 1..=level(&rb, cross) {
+            let (previous_time, previous_moves) = splits
+                .last()
+                .map_or((Duration::ZERO, 0), |split| (split.time, split.moves_total));
+            splits.push(Split {
+                stage: Stage::from_level(reached),
+                time,
+                duration: time - previous_time,
+                moves: moves - previous_moves,
+                moves_total: moves,
+            });
+        }
+    }
+    Some(splits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        notation::parse_moves,
+        replay::TimedTurn,
+        solve_timer::{Penalty, SolveResult},
+    };
+
+    // every turn of the solution takes one second
+    fn replay(scramble: &str, solution: &str) -> Replay {
+        Replay {
+            cube_size: 3,
+            scramble: parse_moves(scramble).unwrap(),
+            turns: moves_layer_turns(&parse_moves(solution).unwrap(), 3)
+                .into_iter()
+                .enumerate()
+                .map(|(i, turn)| TimedTurn {
+                    time: Duration::from_secs(i as u64 + 1),
+                    turn,
+                })
+                .collect(),
+            result: SolveResult {
+                time: Duration::ZERO,
+                penalty: Penalty::None,
+            },
+            date: 0,
+        }
+    }
+
+    fn stages(splits: &[Split]) -> Vec<(Stage, u32)> {
+        splits
+            .iter()
+            .map(|split| (split.stage, split.moves))
+            .collect()
+    }
+
+    #[test]
+    fn cfop_last_layer() {
+        // sune and T perm only touch the last layer, so the cross and
+        // F2L are solved before the first turn
+        let sune = "R U R' U R U2 R'";
+        let t_perm = "R U R' U' R' F R2 U' R' U' R U R' F'";
+        let scramble = format!("{t_perm} {sune}");
+        let solution = format!("R U2 R' U' R U' R' {t_perm}");
+        let splits = analyze(&replay(&scramble, &solution)).unwrap();
+        assert_eq!(
+            stages(&splits),
+            [
+                (Stage::Cross, 0),
+                (Stage::F2l(1), 0),
+                (Stage::F2l(2), 0),
+                (Stage::F2l(3), 0),
+                (Stage::F2l(4), 0),
+                (Stage::Oll, 7),
+                (Stage::Pll, 14),
+            ]
+        );
+        let pll = splits[6];
+        assert_eq!(pll.time, Duration::from_secs(22));
+        assert_eq!(pll.duration, Duration::from_secs(15));
+        assert_eq!(pll.moves_total, 21);
+        assert_eq!(pll.tps(), 14.0 / 15.0);
+    }
+
+    #[test]
+    fn cfop_pair_and_rotation() {
+        // the front right pair is taken out by the scramble
+        let splits = analyze(&replay("R U R'", "y' y R U' R'")).unwrap();
+        assert_eq!(
+            stages(&splits),
+            [
+                (Stage::Cross, 0),
+                (Stage::F2l(1), 0),
+                (Stage::F2l(2), 0),
+                (Stage::F2l(3), 0),
+                (Stage::F2l(4), 3),
+                (Stage::Oll, 0),
+                (Stage::Pll, 0),
+            ]
+        );
+        assert_eq!(splits[4].time, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn cfop_half_turn_metric() {
+        let splits = analyze(&replay("M2 U2 R'", "R U2 M2")).unwrap();
+        assert_eq!(splits.last().unwrap().moves_total, 4);
+        // the same quarter turn twice in a row is a half turn
+        let splits = analyze(&replay("R2 U R'", "R U' R R")).unwrap();
+        assert_eq!(splits.last().unwrap().moves_total, 3);
+    }
+
+    #[test]
+    fn cfop_other_sizes() {
+        let mut replay = replay("R", "R'");
+        replay.cube_size = 4;
+        assert_eq!(analyze(&replay), None);
+    }
+}
@@ -0,0 +1,382 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blindfolded::Blindfolded, cube_material::CubeMaterial, persistence,
+    rubiks_cube_plugin::SubCubeMaterial,
+};
+
+const COLOR_SCHEME_FILE: &str = "color_scheme.ron";
+
+pub const FACE_NAMES: [&str; 6] = ["Up", "Down", "Front", "Back", "Right", "Left"];
+
+// a theme is a directory in the assets with a picture for each face
+pub const PICTURE_THEMES_DIR: &str = "themes";
+const PICTURE_FILES: [&str; 6] = ["up", "down", "front", "back", "right", "left"];
+
+const ORANGE: Color = Color::rgb(1.0, 0.35, 0.0);
+
+pub struct ColorSchemePlugin;
+
+impl Plugin for ColorSchemePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(
+            persistence::load::<ColorScheme>(COLOR_SCHEME_FILE).unwrap_or_default(),
+        );
+
+        app.add_system(load_pictures);
+        app.add_system(recolor_stickers.after(load_pictures));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorPreset {
+    // the colors the game always had
+    Classic,
+    Western,
+    Japanese,
+    HighContrast,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl ColorPreset {
+    pub const ALL: [ColorPreset; 6] = [
+        ColorPreset::Classic,
+        ColorPreset::Western,
+        ColorPreset::Japanese,
+        ColorPreset::HighContrast,
+        ColorPreset::Deuteranopia,
+        ColorPreset::Protanopia,
+    ];
+
+    /// Colors of the U, D, F, B, R and L faces. The colorblind presets use
+    /// the Okabe-Ito palette and keep red and green apart.
+    pub fn faces(self) -> [Color; 6] {
+        match self {
+            ColorPreset::Classic => [
+                Color::GREEN,
+                Color::BLUE,
+                Color::YELLOW,
+                Color::WHITE,
+                Color::RED,
+                ORANGE,
+            ],
+            ColorPreset::Western => [
+                Color::WHITE,
+                Color::YELLOW,
+                Color::GREEN,
+                Color::BLUE,
+                Color::RED,
+                ORANGE,
+            ],
+            ColorPreset::Japanese => [
+                Color::WHITE,
+                Color::BLUE,
+                Color::GREEN,
+                Color::YELLOW,
+                Color::RED,
+                ORANGE,
+            ],
+            ColorPreset::HighContrast => [
+                Color::WHITE,
+                Color::YELLOW,
+                Color::rgb(0.0, 0.8, 0.0),
+                Color::rgb(0.0, 0.25, 1.0),
+                Color::RED,
+                Color::FUCHSIA,
+            ],
+            ColorPreset::Deuteranopia => [
+                Color::WHITE,
+                Color::rgb_u8(240, 228, 66),
+                Color::rgb_u8(86, 180, 233),
+                Color::rgb_u8(0, 114, 178),
+                Color::rgb_u8(213, 94, 0),
+                Color::rgb_u8(204, 121, 167),
+            ],
+            // reds look dark to protanopes, so the orange replaces the vermillion
+            ColorPreset::Protanopia => [
+                Color::WHITE,
+                Color::rgb_u8(240, 228, 66),
+                Color::rgb_u8(0, 158, 115),
+                Color::rgb_u8(0, 114, 178),
+                Color::rgb_u8(230, 159, 0),
+                Color::rgb_u8(204, 121, 167),
+            ],
+        }
+    }
+}
+
+/// Symbol drawn on top of the sticker color, so faces can be told apart
+/// without the colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StickerPattern {
+    #[default]
+    None,
+    Stripes,
+    Dots,
+    Ring,
+    Cross,
+    Square,
+    Triangle,
+}
+
+impl StickerPattern {
+    pub const ALL: [StickerPattern; 7] = [
+        StickerPattern::None,
+        StickerPattern::Stripes,
+        StickerPattern::Dots,
+        StickerPattern::Ring,
+        StickerPattern::Cross,
+        StickerPattern::Square,
+        StickerPattern::Triangle,
+    ];
+
+    // a different pattern for each face, the order of `FACE_NAMES`
+    pub const DISTINCT: [StickerPattern; 6] = [
+        StickerPattern::None,
+        StickerPattern::Dots,
+        StickerPattern::Cross,
+        StickerPattern::Ring,
+        StickerPattern::Stripes,
+        StickerPattern::Triangle,
+    ];
+}
+
+/// Size of the stickers on the faces of the sub cubes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StickerShape {
+    // black border around the sticker as a part of the face width
+    pub border: f32,
+    // radius of the sticker corners as a part of the face width
+    pub radius: f32,
+}
+
+impl Default for StickerShape {
+    fn default() -> Self {
+        Self {
+            border: 0.05,
+            radius: 0.15,
+        }
+    }
+}
+
+impl StickerShape {
+    pub const MAX_BORDER: f32 = 0.2;
+    // round stickers
+    pub const MAX_RADIUS: f32 = 0.5;
+
+    /// The shape within the limits of the settings, edited files may be off.
+    pub fn clamped(self) -> Self {
+        Self {
+            border: self.border.clamp(0.0, Self::MAX_BORDER),
+            radius: self.radius.clamp(0.0, Self::MAX_RADIUS),
+        }
+    }
+}
+
+/// Sticker colors and patterns of the faces in the order of `FACE_NAMES`.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorScheme {
+    // the preset the colors started from, `None` once a color is picked
+    pub preset: Option<ColorPreset>,
+    pub faces: [Color; 6],
+    // older files have no patterns
+    #[serde(default)]
+    pub patterns: [StickerPattern; 6],
+    // pictures cover the colors and patterns of their faces
+    #[serde(default)]
+    pub picture_theme: Option<String>,
+    #[serde(default)]
+    pub sticker_shape: StickerShape,
+    // loaded from the picture theme
+    #[serde(skip)]
+    pub pictures: [Option<Handle<Image>>; 6],
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self::from_preset(ColorPreset::Classic)
+    }
+}
+
+impl ColorScheme {
+    pub fn from_preset(preset: ColorPreset) -> Self {
+        Self {
+            preset: Some(preset),
+            faces: preset.faces(),
+            patterns: default(),
+            picture_theme: None,
+            sticker_shape: default(),
+            pictures: default(),
+        }
+    }
+
+    // keeps the patterns
+    pub fn apply_preset(&mut self, preset: ColorPreset) {
+        self.preset = Some(preset);
+        self.faces = preset.faces();
+    }
+
+    /// Colors in the order of `CubeMaterial::colors`, which the shader
+    /// indexes by the normal of the face.
+    pub fn material_colors(&self) -> [Color; 7] {
+        let [up, down, front, back, right, left] = self.faces;
+        [back, down, left, Color::NONE, right, up, front]
+    }
+
+    /// Patterns in the order of `CubeMaterial::patterns`.
+    pub fn material_patterns(&self) -> [u32; 7] {
+        let [up, down, front, back, right, left] = self.patterns.map(|pattern| pattern as u32);
+        [back, down, left, 0, right, up, front]
+    }
+
+    /// Pictures in the order of `CubeMaterial::colors`.
+    pub fn material_pictures(&self) -> [Option<Handle<Image>>; 7] {
+        let [up, down, front, back, right, left] = self.pictures.clone();
+        [back, down, left, None, right, up, front]
+    }
+
+    pub fn save(&self) {
+        persistence::save(COLOR_SCHEME_FILE, self);
+    }
+}
+
+fn load_pictures(asset_server: Res<AssetServer>, mut color_scheme: ResMut<ColorScheme>) {
+    if !color_scheme.is_changed() {
+        return;
+    }
+    // pictures that never load would keep the sub cubes from being drawn,
+    // so missing files leave the face colored
+    let pictures = color_scheme
+        .picture_theme
+        .as_ref()
+        .map_or_else(default, |theme| {
+            PICTURE_FILES.map(|file| {
+                let path = Path::new(PICTURE_THEMES_DIR)
+                    .join(theme)
+                    .join(format!("{file}.png"));
+                asset_server
+                    .asset_io()
+                    .is_file(&path)
+                    .then(|| asset_server.load(path))
+            })
+        });
+    // loading the same file gives the same handle, so this settles
+    if pictures != color_scheme.pictures {
+        color_scheme.pictures = pictures;
+    }
+}
+
+fn recolor_stickers(
+    color_scheme: Res<ColorScheme>,
+    blindfolded: Res<Blindfolded>,
+    sub_cube_material: Option<Res<SubCubeMaterial>>,
+    mut materials: ResMut<Assets<CubeMaterial>>,
+) {
+    // a new cube is spawned with the current colors
+    let Some(sub_cube_material) = sub_cube_material else {
+        return;
+    };
+    if color_scheme.is_changed() && !sub_cube_material.is_added() {
+        sub_cube_material.paint(
+            blindfolded.phase.stickers_hidden(),
+            &color_scheme,
+            &mut materials,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_scheme() {
+        let scheme = ColorScheme::from_preset(ColorPreset::Western);
+        let colors = scheme.material_colors();
+        // the shader index of a normal is `(normal + 1) . (1, 2, 3) - 3`
+        let index = |normal: IVec3| ((normal + 1).dot(IVec3::new(1, 2, 3)) - 3) as usize;
+        assert_eq!(colors[index(IVec3::Y)], Color::WHITE);
+        assert_eq!(colors[index(IVec3::NEG_Y)], Color::YELLOW);
+        assert_eq!(colors[index(IVec3::Z)], Color::GREEN);
+        assert_eq!(colors[index(IVec3::NEG_Z)], Color::BLUE);
+        assert_eq!(colors[index(IVec3::X)], Color::RED);
+        assert_eq!(colors[index(IVec3::NEG_X)], ORANGE);
+
+        let mut scheme = scheme;
+        scheme.patterns = StickerPattern::DISTINCT;
+        scheme.apply_preset(ColorPreset::Deuteranopia);
+        assert_eq!(scheme.patterns, StickerPattern::DISTINCT);
+        let patterns = scheme.material_patterns();
+        assert_eq!(patterns[index(IVec3::Y)], 0);
+        assert_eq!(patterns[index(IVec3::NEG_Y)], StickerPattern::Dots as u32);
+        assert_eq!(
+            patterns[index(IVec3::NEG_X)],
+            StickerPattern::Triangle as u32
+        );
+
+        for preset in ColorPreset::ALL {
+            let faces = preset.faces();
+            for (i, color) in faces.iter().enumerate() {
+                assert!(!faces[i + 1..].contains(color), "{preset:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn sticker_shape_limits() {
+        let shape = StickerShape::default();
+        assert_eq!(shape.clamped(), shape);
+        let shape = StickerShape {
+            border: 0.5,
+            radius: -0.1,
+        };
+        assert_eq!(
+            shape.clamped(),
+            StickerShape {
+                border: StickerShape::MAX_BORDER,
+                radius: 0.0,
+            }
+        );
+        let shape = StickerShape {
+            border: -1.0,
+            radius: 2.0,
+        };
+        assert_eq!(
+            shape.clamped(),
+            StickerShape {
+                border: 0.0,
+                radius: StickerShape::MAX_RADIUS,
+            }
+        );
+    }
+
+    #[test]
+    fn color_scheme_file() {
+        let mut scheme = ColorScheme::from_preset(ColorPreset::Japanese);
+        scheme.patterns = StickerPattern::DISTINCT;
+        scheme.picture_theme = Some("arrows".to_string());
+        scheme.sticker_shape.radius = 0.4;
+        let saved = ron::to_string(&scheme).unwrap();
+        assert_eq!(ron::from_str::<ColorScheme>(&saved).unwrap(), scheme);
+
+        // files from before the patterns and sticker shapes
+        #[derive(Serialize)]
+        struct OldScheme {
+            preset: Option<ColorPreset>,
+            faces: [Color; 6],
+        }
+        let old = ron::to_string(&OldScheme {
+            preset: None,
+            faces: ColorPreset::Western.faces(),
+        })
+        .unwrap();
+        let scheme = ron::from_str::<ColorScheme>(&old).unwrap();
+        assert_eq!(scheme.faces, ColorPreset::Western.faces());
+        assert_eq!(scheme.patterns, [StickerPattern::None; 6]);
+        assert_eq!(scheme.sticker_shape, StickerShape::default());
+    }
+}
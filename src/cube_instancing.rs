@@ -0,0 +1,428 @@
+use bevy::{
+    core_pipeline::{core_3d::Opaque3d, tonemapping::Tonemapping},
+    ecs::system::{lifetimeless::*, SystemParamItem},
+    pbr::{
+        MaterialPipeline, MeshPipeline, MeshPipelineKey, MeshUniform, RenderMaterials,
+        SetMeshBindGroup, SetMeshViewBindGroup, SetShadowViewBindGroup, Shadow, ShadowPipeline,
+        ShadowPipelineKey,
+    },
+    prelude::*,
+    render::{
+        mesh::{GpuBufferInfo, MeshVertexBufferLayout},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, EntityRenderCommand, RenderCommandResult, RenderPhase,
+            SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::RenderDevice,
+        view::ExtractedView,
+        Extract, RenderApp, RenderStage,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::cube_material::CubeMaterial;
+
+const SHADOW_SHADER: &str = "shaders/cube_shadow.wgsl";
+// shader locations 0-7 are left to the mesh attributes
+const FIRST_INSTANCE_LOCATION: u32 = 8;
+
+/// Draws every sub cube of a cube with a single instanced draw call, so big
+/// cubes cost as much as the small ones. The entity holds the sub cube mesh
+/// and its children with a `SubCubeInstance` are the instances.
+pub struct CubeInstancingPlugin;
+
+impl Plugin for CubeInstancingPlugin {
+    fn build(&self, app: &mut App) {
+        // prepares the bind group of the material shared by the sub cubes
+        app.add_plugin(MaterialPlugin::<CubeMaterial>::default());
+
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .add_render_command::<Opaque3d, DrawSubCubes>()
+                .add_render_command::<Shadow, DrawSubCubeShadows>()
+                .init_resource::<SubCubePipeline>()
+                .init_resource::<SubCubeShadowPipeline>()
+                .init_resource::<SpecializedMeshPipelines<SubCubePipeline>>()
+                .init_resource::<SpecializedMeshPipelines<SubCubeShadowPipeline>>()
+                .add_system_to_stage(RenderStage::Extract, extract_sub_cubes)
+                .add_system_to_stage(RenderStage::Prepare, prepare_instance_buffers)
+                .add_system_to_stage(RenderStage::Queue, queue_sub_cubes)
+                .add_system_to_stage(RenderStage::Queue, queue_sub_cube_shadows);
+        }
+    }
+}
+
+/// Cube drawn with instancing, the material is shared by all sub cubes.
+#[derive(Component, Debug, Clone)]
+pub struct InstancedSubCubes {
+    pub material: Handle<CubeMaterial>,
+}
+
+/// Sub cube drawn by the `InstancedSubCubes` of its parent.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SubCubeInstance {
+    // bit mask of the faces that show stickers in the order of the
+    // `CubeMaterial::colors`, the other faces are black
+    pub outer_faces: u8,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct InstanceData {
+    // transform of the sub cube relative to the cube
+    transform: Mat4,
+    outer_faces: u32,
+    // the matrix aligns the size to 16 bytes
+    _padding: [u32; 3],
+}
+
+#[derive(Component)]
+struct ExtractedSubCubes {
+    material: Handle<CubeMaterial>,
+    instances: Vec<InstanceData>,
+}
+
+#[derive(Component)]
+struct InstanceBuffer {
+    buffer: Buffer,
+    length: usize,
+}
+
+fn extract_sub_cubes(
+    mut commands: Commands,
+    cubes: Extract<Query<(Entity, &ComputedVisibility, &InstancedSubCubes, &Children)>>,
+    sub_cubes: Extract<Query<(&Transform, &SubCubeInstance)>>,
+) {
+    for (entity, visibility, instanced, children) in cubes.iter() {
+        if !visibility.is_visible() {
+            continue;
+        }
+        let instances = sub_cubes
+            .iter_many(children)
+            .map(|(transform, instance)| InstanceData {
+                transform: transform.compute_matrix(),
+                outer_faces: instance.outer_faces as u32,
+                _padding: [0; 3],
+            })
+            .collect();
+        commands.get_or_spawn(entity).insert(ExtractedSubCubes {
+            material: instanced.material.clone_weak(),
+            instances,
+        });
+    }
+}
+
+fn prepare_instance_buffers(
+    mut commands: Commands,
+    query: Query<(Entity, &ExtractedSubCubes)>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, sub_cubes) in query.iter() {
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("sub cube instance buffer"),
+            contents: bytemuck::cast_slice(&sub_cubes.instances),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        commands.entity(entity).insert(InstanceBuffer {
+            buffer,
+            length: sub_cubes.instances.len(),
+        });
+    }
+}
+
+// the transform takes four locations and the shadows only need the transform
+fn instance_buffer_layout(with_outer_faces: bool) -> VertexBufferLayout {
+    let mut attributes: Vec<_> = (0..4)
+        .map(|column| VertexAttribute {
+            format: VertexFormat::Float32x4,
+            offset: column * VertexFormat::Float32x4.size(),
+            shader_location: FIRST_INSTANCE_LOCATION + column as u32,
+        })
+        .collect();
+    if with_outer_faces {
+        attributes.push(VertexAttribute {
+            format: VertexFormat::Uint32,
+            offset: std::mem::size_of::<Mat4>() as u64,
+            shader_location: FIRST_INSTANCE_LOCATION + 4,
+        });
+    }
+    VertexBufferLayout {
+        array_stride: std::mem::size_of::<InstanceData>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_sub_cubes(
+    opaque_draw_functions: Res<DrawFunctions<Opaque3d>>,
+    sub_cube_pipeline: Res<SubCubePipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<SubCubePipeline>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    render_materials: Res<RenderMaterials<CubeMaterial>>,
+    sub_cubes: Query<(Entity, &ExtractedSubCubes, &Handle<Mesh>, &MeshUniform)>,
+    mut views: Query<(
+        &ExtractedView,
+        Option<&Tonemapping>,
+        &mut RenderPhase<Opaque3d>,
+    )>,
+) {
+    let draw_sub_cubes = opaque_draw_functions
+        .read()
+        .get_id::<DrawSubCubes>()
+        .unwrap();
+
+    for (view, tonemapping, mut opaque_phase) in views.iter_mut() {
+        // the same view key as the one of the material meshes
+        let mut view_key =
+            MeshPipelineKey::from_msaa_samples(msaa.samples) | MeshPipelineKey::from_hdr(view.hdr);
+        if let Some(Tonemapping::Enabled { deband_dither }) = tonemapping {
+            if !view.hdr {
+                view_key |= MeshPipelineKey::TONEMAP_IN_SHADER;
+                if *deband_dither {
+                    view_key |= MeshPipelineKey::DEBAND_DITHER;
+                }
+            }
+        }
+        let rangefinder = view.rangefinder3d();
+
+        for (entity, extracted, mesh_handle, mesh_uniform) in sub_cubes.iter() {
+            // the material is not prepared until its pictures are loaded
+            if !render_materials.contains_key(&extracted.material) {
+                continue;
+            }
+            let Some(mesh) = render_meshes.get(mesh_handle) else {
+                continue;
+            };
+            let key = view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            let pipeline = match pipelines.specialize(
+                &mut pipeline_cache,
+                &sub_cube_pipeline,
+                key,
+                &mesh.layout,
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+            opaque_phase.add(Opaque3d {
+                entity,
+                pipeline,
+                draw_function: draw_sub_cubes,
+                distance: rangefinder.distance(&mesh_uniform.transform),
+            });
+        }
+    }
+}
+
+// the cube is not culled per light, it is in the middle of the scene
+fn queue_sub_cube_shadows(
+    shadow_draw_functions: Res<DrawFunctions<Shadow>>,
+    shadow_pipeline: Res<SubCubeShadowPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<SubCubeShadowPipeline>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    sub_cubes: Query<(Entity, &Handle<Mesh>), With<ExtractedSubCubes>>,
+    mut shadow_phases: Query<&mut RenderPhase<Shadow>>,
+) {
+    let draw_shadows = shadow_draw_functions
+        .read()
+        .get_id::<DrawSubCubeShadows>()
+        .unwrap();
+
+    for (entity, mesh_handle) in sub_cubes.iter() {
+        let Some(mesh) = render_meshes.get(mesh_handle) else {
+            continue;
+        };
+        let key = ShadowPipelineKey::from_primitive_topology(mesh.primitive_topology);
+        let pipeline =
+            match pipelines.specialize(&mut pipeline_cache, &shadow_pipeline, key, &mesh.layout) {
+                Ok(pipeline) => pipeline,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+        for mut shadow_phase in shadow_phases.iter_mut() {
+            shadow_phase.add(Shadow {
+                entity,
+                pipeline,
+                draw_function: draw_shadows,
+                distance: 0.0,
+            });
+        }
+    }
+}
+
+// `MaterialPipeline<CubeMaterial>` with the instance buffer
+#[derive(Resource)]
+struct SubCubePipeline {
+    mesh_pipeline: MeshPipeline,
+    material_layout: BindGroupLayout,
+    vertex_shader: Option<Handle<Shader>>,
+    fragment_shader: Option<Handle<Shader>>,
+}
+
+impl FromWorld for SubCubePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let material_pipeline = world.resource::<MaterialPipeline<CubeMaterial>>();
+        Self {
+            mesh_pipeline: material_pipeline.mesh_pipeline.clone(),
+            material_layout: material_pipeline.material_layout.clone(),
+            vertex_shader: material_pipeline.vertex_shader.clone(),
+            fragment_shader: material_pipeline.fragment_shader.clone(),
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for SubCubePipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        if let Some(vertex_shader) = &self.vertex_shader {
+            descriptor.vertex.shader = vertex_shader.clone();
+        }
+        if let Some(fragment_shader) = &self.fragment_shader {
+            descriptor.fragment.as_mut().unwrap().shader = fragment_shader.clone();
+        }
+        descriptor.vertex.buffers.push(instance_buffer_layout(true));
+        descriptor
+            .layout
+            .as_mut()
+            .unwrap()
+            .insert(1, self.material_layout.clone());
+        Ok(descriptor)
+    }
+}
+
+// `ShadowPipeline` with the instance buffer
+#[derive(Resource)]
+struct SubCubeShadowPipeline {
+    shadow_pipeline: ShadowPipeline,
+    shader: Handle<Shader>,
+}
+
+impl FromWorld for SubCubeShadowPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shadow_pipeline = world.resource::<ShadowPipeline>();
+        let shadow_pipeline = ShadowPipeline {
+            view_layout: shadow_pipeline.view_layout.clone(),
+            mesh_layout: shadow_pipeline.mesh_layout.clone(),
+            skinned_mesh_layout: shadow_pipeline.skinned_mesh_layout.clone(),
+            point_light_sampler: shadow_pipeline.point_light_sampler.clone(),
+            directional_light_sampler: shadow_pipeline.directional_light_sampler.clone(),
+        };
+        Self {
+            shadow_pipeline,
+            shader: world.resource::<AssetServer>().load(SHADOW_SHADER),
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for SubCubeShadowPipeline {
+    type Key = ShadowPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.shadow_pipeline.specialize(key, layout)?;
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor
+            .vertex
+            .buffers
+            .push(instance_buffer_layout(false));
+        Ok(descriptor)
+    }
+}
+
+type DrawSubCubes = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetSubCubeMaterialBindGroup<1>,
+    SetMeshBindGroup<2>,
+    DrawSubCubeInstances,
+);
+
+type DrawSubCubeShadows = (
+    SetItemPipeline,
+    SetShadowViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    DrawSubCubeInstances,
+);
+
+struct SetSubCubeMaterialBindGroup<const I: usize>;
+
+impl<const I: usize> EntityRenderCommand for SetSubCubeMaterialBindGroup<I> {
+    type Param = (
+        SRes<RenderMaterials<CubeMaterial>>,
+        SQuery<Read<ExtractedSubCubes>>,
+    );
+
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (materials, sub_cubes): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let sub_cubes = sub_cubes.get_inner(item).unwrap();
+        let Some(material) = materials.into_inner().get(&sub_cubes.material) else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_bind_group(I, &material.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawSubCubeInstances;
+
+impl EntityRenderCommand for DrawSubCubeInstances {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SQuery<Read<Handle<Mesh>>>,
+        SQuery<Read<InstanceBuffer>>,
+    );
+
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (meshes, mesh_query, instance_buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let mesh_handle = mesh_query.get(item).unwrap();
+        let instance_buffer = instance_buffers.get_inner(item).unwrap();
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_handle) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        let instances = 0..instance_buffer.length as u32;
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed { vertex_count } => {
+                pass.draw(0..*vertex_count, instances);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
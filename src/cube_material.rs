@@ -7,6 +7,11 @@ use bevy::{
     },
 };
 
+use crate::{
+    color_scheme::{ColorScheme, StickerShape},
+    rubiks_cube_plugin::{CUBE_SIDE_SIZE, CUBE_SPACING},
+};
+
 #[derive(AsBindGroup, TypeUuid, Debug, Clone)]
 #[uuid = "f690fdae-d598-45ab-8225-97e2a3f056e0"]
 #[uniform(0, CubeMaterialUniform)]
@@ -16,6 +21,69 @@ pub struct CubeMaterial {
     pub metallic: f32,
     pub reflectance: f32,
     pub colors: [Color; 7],
+    // `StickerPattern` of each face in the order of the colors
+    pub patterns: [u32; 7],
+    // the centers show which way they are turned
+    pub center_markers: bool,
+    // half the side of the sub cube mesh
+    pub half_size: f32,
+    // the `StickerShape`
+    pub sticker_border: f32,
+    pub sticker_radius: f32,
+    // sub cubes along an edge of the cube and the distance between their
+    // centers, pictures span the whole face of the cube
+    pub cube_sides: u32,
+    pub sub_cube_spacing: f32,
+    // pictures replace the colors of their faces
+    #[texture(1)]
+    #[sampler(2)]
+    pub back_picture: Option<Handle<Image>>,
+    #[texture(3)]
+    #[sampler(4)]
+    pub down_picture: Option<Handle<Image>>,
+    #[texture(5)]
+    #[sampler(6)]
+    pub left_picture: Option<Handle<Image>>,
+    #[texture(7)]
+    #[sampler(8)]
+    pub right_picture: Option<Handle<Image>>,
+    #[texture(9)]
+    #[sampler(10)]
+    pub up_picture: Option<Handle<Image>>,
+    #[texture(11)]
+    #[sampler(12)]
+    pub front_picture: Option<Handle<Image>>,
+}
+
+impl CubeMaterial {
+    /// Sets the pictures in the order of the colors.
+    pub fn set_pictures(&mut self, pictures: [Option<Handle<Image>>; 7]) {
+        let [back, down, left, _, right, up, front] = pictures;
+        self.back_picture = back;
+        self.down_picture = down;
+        self.left_picture = left;
+        self.right_picture = right;
+        self.up_picture = up;
+        self.front_picture = front;
+    }
+
+    // bit mask of the faces with a picture in the order of the colors
+    fn picture_faces(&self) -> u32 {
+        [
+            &self.back_picture,
+            &self.down_picture,
+            &self.left_picture,
+            &None,
+            &self.right_picture,
+            &self.up_picture,
+            &self.front_picture,
+        ]
+        .into_iter()
+        .enumerate()
+        .fold(0, |mask, (i, picture)| {
+            mask | ((picture.is_some() as u32) << i)
+        })
+    }
 }
 
 impl Default for CubeMaterial {
@@ -25,15 +93,20 @@ impl Default for CubeMaterial {
             perceptual_roughness: 0.69,
             metallic: 0.001,
             reflectance: 0.01,
-            colors: [
-                Color::RED,
-                Color::GREEN,
-                Color::YELLOW,
-                Color::NONE,
-                Color::WHITE,
-                Color::BLUE,
-                Color::rgb(1.0, 0.35, 0.0),
-            ],
+            colors: ColorScheme::default().material_colors(),
+            patterns: [0; 7],
+            center_markers: false,
+            half_size: CUBE_SIDE_SIZE / 2.0,
+            sticker_border: StickerShape::default().border,
+            sticker_radius: StickerShape::default().radius,
+            cube_sides: 3,
+            sub_cube_spacing: CUBE_SPACING,
+            back_picture: None,
+            down_picture: None,
+            left_picture: None,
+            right_picture: None,
+            up_picture: None,
+            front_picture: None,
         }
     }
 }
@@ -60,6 +133,15 @@ pub struct CubeMaterialUniform {
     pub reflectance: f32,
     pub alpha_cutoff: f32,
     pub colors: [Vec4; 7],
+    // only x is used, arrays in uniforms have a 16 byte stride
+    pub patterns: [UVec4; 7],
+    pub picture_faces: u32,
+    pub center_markers: u32,
+    pub half_size: f32,
+    pub sticker_border: f32,
+    pub sticker_radius: f32,
+    pub cube_sides: u32,
+    pub sub_cube_spacing: f32,
 }
 
 impl AsBindGroupShaderType<CubeMaterialUniform> for CubeMaterial {
@@ -71,6 +153,14 @@ impl AsBindGroupShaderType<CubeMaterialUniform> for CubeMaterial {
             reflectance: self.reflectance,
             alpha_cutoff: 0.5,
             colors: self.colors.map(Into::into),
+            patterns: self.patterns.map(|pattern| UVec4::new(pattern, 0, 0, 0)),
+            picture_faces: self.picture_faces(),
+            center_markers: self.center_markers as u32,
+            half_size: self.half_size,
+            sticker_border: self.sticker_border,
+            sticker_radius: self.sticker_radius,
+            cube_sides: self.cube_sides,
+            sub_cube_spacing: self.sub_cube_spacing,
         }
     }
 }
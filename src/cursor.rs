@@ -1,20 +1,28 @@
 use bevy::prelude::*;
 
-use crate::{rubiks_cube_plugin::CurrentlySelectedSubCubeRayNormal, GameStates};
+use crate::{
+    input_bindings::{Action, ActionInput},
+    rubiks_cube_plugin::CurrentlySelectedSubCubeRayNormal,
+    GameStates,
+};
 
 pub struct CursorRayPlugin;
 
 impl Plugin for CursorRayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameStates::InGame).with_system(init));
-        app.add_system_set(
-            SystemSet::on_update(GameStates::InGame)
-                .with_system(world_cursor_system)
-                .with_system(cursor_selection_vector)
-                .with_system(selection_vector_colliniar_axis.after(cursor_selection_vector))
-                .with_system(projection_on_collinear_axis.after(selection_vector_colliniar_axis)),
-        );
-        app.add_system_set(SystemSet::on_exit(GameStates::InGame).with_system(deinit));
+        for state in [GameStates::InGame, GameStates::Trainer, GameStates::Fmc] {
+            app.add_system_set(SystemSet::on_enter(state).with_system(init));
+            app.add_system_set(
+                SystemSet::on_update(state)
+                    .with_system(world_cursor_system)
+                    .with_system(cursor_selection_vector)
+                    .with_system(selection_vector_colliniar_axis.after(cursor_selection_vector))
+                    .with_system(
+                        projection_on_collinear_axis.after(selection_vector_colliniar_axis),
+                    ),
+            );
+            app.add_system_set(SystemSet::on_exit(state).with_system(deinit));
+        }
     }
 }
 
@@ -68,11 +76,11 @@ fn world_cursor_system(
 }
 
 fn cursor_selection_vector(
-    mouse_input: Res<Input<MouseButton>>,
+    action_input: ActionInput,
     windows: Res<Windows>,
     mut crs_vector: ResMut<CursorSelectionVector>,
 ) {
-    if mouse_input.just_pressed(MouseButton::Left) {
+    if action_input.just_pressed(Action::TurnLayer) {
         let window = windows
             .get_primary()
             .expect("We need a primary window to play the game");
@@ -82,7 +90,7 @@ fn cursor_selection_vector(
                 end: screen_pos,
             });
         }
-    } else if mouse_input.pressed(MouseButton::Left) {
+    } else if action_input.pressed(Action::TurnLayer) {
         let window = windows
             .get_primary()
             .expect("We need a primary window to play the game");
@@ -91,7 +99,7 @@ fn cursor_selection_vector(
                 vec.end = screen_pos;
             }
         }
-    } else if mouse_input.just_released(MouseButton::Left) {
+    } else if action_input.just_released(Action::TurnLayer) {
         crs_vector.0 = None;
     }
 }
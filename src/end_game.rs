@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    game_state::{update_solve_timer, GameState, RecordSolve},
+    rubiks_cube::RubiksCube,
+    solve_timer::{SolveResult, SolveTimer},
+    statistics::{Solve, Solves},
+    ui::UiStates,
+    GameStates,
+};
+
+// full turns of the cube per second while celebrating
+const SPIN_SPEED: f32 = 0.5;
+const BOUNCE_HEIGHT: f32 = 0.05;
+
+pub struct EndGamePlugin;
+
+impl Plugin for EndGamePlugin {
+    fn build(&self, app: &mut App) {
+        // the solve and its replay are recorded before the game is paused
+        app.add_system_set(
+            SystemSet::on_update(GameStates::InGame)
+                .with_system(enter_end_game.after(update_solve_timer).after(RecordSolve)),
+        );
+        app.add_system_set(SystemSet::on_update(GameStates::EndGame).with_system(celebrate));
+        app.add_system_set(SystemSet::on_exit(GameStates::EndGame).with_system(stop_celebration));
+    }
+}
+
+/// Results of the last solve compared with the earlier ones of the size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveSummary {
+    pub result: SolveResult,
+    pub moves: u32,
+    pub cube_size: u32,
+    // best final time before this solve
+    pub previous_best: Option<Duration>,
+}
+
+impl SolveSummary {
+    /// Summary of the latest solve, `None` before the first one.
+    pub fn of_last(solves: &Solves) -> Option<Self> {
+        let (last, earlier) = solves.solves.split_last()?;
+        let previous_best = earlier
+            .iter()
+            .filter(|solve| solve.cube_size == last.cube_size)
+            .filter_map(|solve| solve.result.final_time())
+            .min();
+        let Solve {
+            result,
+            moves,
+            cube_size,
+            ..
+        } = *last;
+        Some(Self {
+            result,
+            moves,
+            cube_size,
+            previous_best,
+        })
+    }
+
+    // turns per second over the time without penalties
+    pub fn tps(&self) -> f32 {
+        let secs = self.result.time.as_secs_f32();
+        if secs > 0.0 {
+            self.moves as f32 / secs
+        } else {
+            0.0
+        }
+    }
+
+    // the first finished solve of a size is a personal best too
+    pub fn is_personal_best(&self) -> bool {
+        match (self.result.final_time(), self.previous_best) {
+            (Some(time), Some(best)) => time < best,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+// the timer finished by solving the cube in this frame, an inspection that
+// runs out finishes the timer on an unsolved cube
+fn solve_finished(previous_timer: &SolveTimer, game_state: &GameState) -> bool {
+    game_state.is_solved
+        && game_state.timer.result().is_some()
+        && !matches!(previous_timer, SolveTimer::Finished(_))
+}
+
+fn enter_end_game(
+    game_state: Res<GameState>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut previous_timer: Local<SolveTimer>,
+) {
+    // the results stay up until the next scramble, so they are shown once
+    let finished = solve_finished(&previous_timer, &game_state);
+    *previous_timer = game_state.timer;
+    if finished {
+        game_states.push(GameStates::EndGame).unwrap();
+        ui_states.push(UiStates::EndGame).unwrap();
+    }
+}
+
+fn celebrate(time: Res<Time>, mut rubiks_cube: Query<&mut Transform, With<RubiksCube>>) {
+    let Ok(mut transform) = rubiks_cube.get_single_mut() else {
+        return;
+    };
+    let elapsed = time.elapsed_seconds();
+    transform.rotation = Quat::from_rotation_y(elapsed * SPIN_SPEED * std::f32::consts::TAU);
+    transform.translation.y = (elapsed * std::f32::consts::TAU).sin().abs() * BOUNCE_HEIGHT;
+}
+
+fn stop_celebration(mut rubiks_cube: Query<&mut Transform, With<RubiksCube>>) {
+    if let Ok(mut transform) = rubiks_cube.get_single_mut() {
+        transform.rotation = Quat::IDENTITY;
+        transform.translation = Vec3::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solve_timer::{Penalty, INSPECTION_LIMIT};
+
+    fn solve(secs: u64, penalty: Penalty, cube_size: u32) -> Solve {
+        Solve {
+            scramble: Vec::new(),
+            result: SolveResult {
+                time: Duration::from_secs(secs),
+                penalty,
+            },
+            moves: 50,
+            date: 0,
+            cube_size,
+            session: 0,
+        }
+    }
+
+    #[test]
+    fn solve_summary() {
+        let mut solves = Solves::default();
+        assert_eq!(SolveSummary::of_last(&solves), None);
+
+        solves.solves.push(solve(20, Penalty::None, 3));
+        let summary = SolveSummary::of_last(&solves).unwrap();
+        assert!(summary.is_personal_best());
+        assert_eq!(summary.tps(), 2.5);
+
+        // other sizes and DNFs do not count
+        solves.solves.push(solve(10, Penalty::None, 4));
+        solves.solves.push(solve(5, Penalty::Dnf, 3));
+        let summary = SolveSummary::of_last(&solves).unwrap();
+        assert!(!summary.is_personal_best());
+        assert_eq!(summary.previous_best, Some(Duration::from_secs(20)));
+
+        solves.solves.push(solve(19, Penalty::PlusTwo, 3));
+        assert!(!SolveSummary::of_last(&solves).unwrap().is_personal_best());
+        solves.solves.push(solve(17, Penalty::PlusTwo, 3));
+        assert!(SolveSummary::of_last(&solves).unwrap().is_personal_best());
+    }
+
+    #[test]
+    fn end_game_after_solve() {
+        let mut game_state = GameState::default();
+        game_state.timer.start_inspection();
+        game_state.timer.turn(false);
+        let previous = game_state.timer;
+        game_state.is_solved = true;
+        game_state.timer.solved();
+        assert!(solve_finished(&previous, &game_state));
+        // the results are shown once
+        assert!(!solve_finished(&game_state.timer, &game_state));
+    }
+
+    #[test]
+    fn no_end_game_after_inspection_dnf() {
+        let mut game_state = GameState::default();
+        game_state.timer.start_inspection();
+        let previous = game_state.timer;
+        game_state
+            .timer
+            .tick(INSPECTION_LIMIT + Duration::from_secs(1));
+        assert_eq!(game_state.timer.result().unwrap().penalty, Penalty::Dnf);
+        assert!(!solve_finished(&previous, &game_state));
+
+        // solving the cube afterwards does not finish the solve again
+        let previous = game_state.timer;
+        game_state.is_solved = true;
+        game_state.timer.solved();
+        assert!(!solve_finished(&previous, &game_state));
+    }
+}
@@ -0,0 +1,300 @@
+use std::{fmt::Display, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    notation::{invert_moves, moves_layer_turns, parse_moves, scramble, Move, ParseMoveError},
+    rubiks_cube::{MoveHistory, RubiksCube},
+    rubiks_cube_plugin::{snap_sub_cubes, MoveQueue, SubCube},
+    GameStates,
+};
+
+pub const FMC_TIME_LIMIT: Duration = Duration::from_secs(60 * 60);
+
+pub struct FmcPlugin;
+
+impl Plugin for FmcPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Fmc::default());
+
+        app.add_system_set(SystemSet::on_enter(GameStates::Fmc).with_system(start_fmc));
+        app.add_system_set(
+            SystemSet::on_update(GameStates::Fmc)
+                .with_system(count_down)
+                .with_system(set_up_sandbox),
+        );
+    }
+}
+
+/// Solution written with NISS, moves in parentheses are done on the
+/// inverse scramble.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NissSolution {
+    pub normal: Vec<Move>,
+    pub inverse: Vec<Move>,
+}
+
+impl NissSolution {
+    /// Parses the solution like `parse_moves`, but parentheses switch to
+    /// the inverse moves. Errors point into the whole text.
+    pub fn parse(s: &str) -> Result<Self, ParseMoveError> {
+        // both parts keep the positions of the text, the other part is blanked
+        let mut normal = String::with_capacity(s.len());
+        let mut inverse = String::with_capacity(s.len());
+        let mut on_inverse = false;
+        let mut comment = false;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\n' {
+                comment = false;
+            } else if c == '/' && chars.peek() == Some(&'/') {
+                comment = true;
+            } else if !comment && c == '(' {
+                on_inverse = true;
+            } else if !comment && c == ')' {
+                on_inverse = false;
+            }
+            let blank = " ".repeat(c.len_utf8());
+            let (kept, blanked) = if on_inverse && !comment {
+                (&mut inverse, &mut normal)
+            } else {
+                (&mut normal, &mut inverse)
+            };
+            kept.push(c);
+            blanked.push_str(if c == '\n' { "\n" } else { &blank });
+        }
+        Ok(Self {
+            normal: parse_moves(&normal)?,
+            inverse: parse_moves(&inverse)?,
+        })
+    }
+
+    /// The solution of the scramble: the normal moves followed by the
+    /// inverse moves undone.
+    pub fn moves(&self) -> Vec<Move> {
+        let mut moves = self.normal.clone();
+        moves.extend(invert_moves(&self.inverse));
+        moves
+    }
+}
+
+pub fn htm(moves: &[Move]) -> usize {
+    moves.iter().map(Move::htm).sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FmcResult {
+    Solved { moves: usize },
+    // the submitted solution does not parse or does not solve the scramble,
+    // running out of time submits the solution written so far
+    Dnf,
+}
+
+impl Display for FmcResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FmcResult::Solved { moves } => write!(f, "{moves} HTM"),
+            FmcResult::Dnf => write!(f, "DNF"),
+        }
+    }
+}
+
+/// Applies the solution to the scrambled 3x3x3 and counts its moves.
+pub fn verify_solution(scramble: &[Move], solution: &[Move]) -> FmcResult {
+    let mut rb = RubiksCube::new(3);
+    for turn in moves_layer_turns(scramble, 3)
+        .iter()
+        .chain(&moves_layer_turns(solution, 3))
+    {
+        rb.turn(turn);
+    }
+    if rb.is_solved() {
+        FmcResult::Solved {
+            moves: htm(solution),
+        }
+    } else {
+        FmcResult::Dnf
+    }
+}
+
+/// Fewest moves attempt, the written solution is checked when submitted
+/// or when the time is up.
+#[derive(Resource, Debug)]
+pub struct Fmc {
+    pub scramble: Vec<Move>,
+    pub solution: String,
+    // the time limit can be changed until the clock is started
+    pub time_limit: Duration,
+    pub time_left: Duration,
+    pub started: bool,
+    // the sandbox shows the inverse scramble with the inverse moves
+    pub inverse_view: bool,
+    // the sandbox cube is set up again once the turns are animated
+    pub sandbox_outdated: bool,
+    pub result: Option<FmcResult>,
+}
+
+impl Default for Fmc {
+    fn default() -> Self {
+        Self {
+            scramble: Vec::new(),
+            solution: String::new(),
+            time_limit: FMC_TIME_LIMIT,
+            time_left: FMC_TIME_LIMIT,
+            started: false,
+            inverse_view: false,
+            sandbox_outdated: false,
+            result: None,
+        }
+    }
+}
+
+impl Fmc {
+    pub fn start(&mut self, scramble: Vec<Move>) {
+        *self = Self {
+            scramble,
+            time_limit: self.time_limit,
+            time_left: self.time_limit,
+            sandbox_outdated: true,
+            ..default()
+        };
+    }
+
+    /// Reveals the scramble and starts counting down the time limit.
+    pub fn start_clock(&mut self) {
+        self.started = true;
+        self.time_left = self.time_limit;
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        if !self.started || self.result.is_some() {
+            return;
+        }
+        self.time_left = self.time_left.saturating_sub(delta);
+        if self.time_left.is_zero() {
+            self.submit();
+        }
+    }
+
+    /// Ends the attempt, solutions that do not parse are DNFs.
+    pub fn submit(&mut self) {
+        if self.result.is_some() {
+            return;
+        }
+        self.result = Some(match NissSolution::parse(&self.solution) {
+            Ok(solution) => verify_solution(&self.scramble, &solution.moves()),
+            Err(_) => FmcResult::Dnf,
+        });
+    }
+
+    /// Moves that set up the sandbox: the scramble and the normal moves, or
+    /// the inverse scramble and the inverse moves.
+    pub fn sandbox_moves(&self) -> Result<Vec<Move>, ParseMoveError> {
+        let solution = NissSolution::parse(&self.solution)?;
+        Ok(if self.inverse_view {
+            let mut moves = invert_moves(&self.scramble);
+            moves.extend(solution.inverse);
+            moves
+        } else {
+            let mut moves = self.scramble.clone();
+            moves.extend(solution.normal);
+            moves
+        })
+    }
+}
+
+fn start_fmc(mut fmc: ResMut<Fmc>) {
+    fmc.start(scramble(3));
+}
+
+fn count_down(time: Res<Time>, mut fmc: ResMut<Fmc>) {
+    fmc.tick(time.delta());
+}
+
+fn set_up_sandbox(
+    mut fmc: ResMut<Fmc>,
+    mut move_queue: ResMut<MoveQueue>,
+    mut rubiks_cube: Query<(&mut RubiksCube, &mut MoveHistory)>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+) {
+    // the scramble is hidden until the clock is started
+    if !fmc.started || !fmc.sandbox_outdated || !move_queue.is_empty() {
+        return;
+    }
+    // the cube is spawned a frame after entering the state
+    let Ok((mut rb, mut history)) = rubiks_cube.get_single_mut() else {
+        return;
+    };
+    fmc.sandbox_outdated = false;
+    // the editor shows the error of a solution that does not parse
+    let Ok(moves) = fmc.sandbox_moves() else {
+        return;
+    };
+
+    move_queue.clear();
+    *history = MoveHistory::default();
+    rb.reset();
+    for turn in moves_layer_turns(&moves, 3) {
+        rb.turn(&turn);
+    }
+    snap_sub_cubes(&rb, &mut sub_cubes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::format_moves;
+
+    #[test]
+    fn niss_solution() {
+        let solution = NissSolution::parse("R U // (not inverse)\n(F2 D') L (B)").unwrap();
+        assert_eq!(format_moves(&solution.normal), "R U L");
+        assert_eq!(format_moves(&solution.inverse), "F2 D' B");
+        assert_eq!(format_moves(&solution.moves()), "R U L B' D F2");
+
+        let error = NissSolution::parse("R (U Q)").unwrap_err();
+        assert_eq!(error.position, 5);
+        assert_eq!(error.token, "Q");
+    }
+
+    #[test]
+    fn fmc_verification() {
+        let scramble = parse_moves("R U F' M").unwrap();
+        // the inverse scramble solves with the scramble itself
+        let solution = NissSolution::parse("M' F (R U)").unwrap();
+        assert_eq!(
+            verify_solution(&scramble, &solution.moves()),
+            FmcResult::Solved { moves: 5 }
+        );
+        assert_eq!(verify_solution(&scramble, &[]), FmcResult::Dnf);
+
+        let mut fmc = Fmc::default();
+        fmc.start(scramble);
+        // the time only runs once the clock is started
+        fmc.tick(Duration::from_secs(60));
+        assert_eq!(fmc.time_left, FMC_TIME_LIMIT);
+        fmc.time_limit = Duration::from_secs(30 * 60);
+        fmc.start_clock();
+        assert_eq!(fmc.time_left, Duration::from_secs(30 * 60));
+        fmc.solution = "M' F (R U)".to_string();
+        fmc.inverse_view = true;
+        assert_eq!(
+            format_moves(&fmc.sandbox_moves().unwrap()),
+            "M' F U' R' R U"
+        );
+        fmc.tick(Duration::from_secs(60));
+        assert_eq!(fmc.result, None);
+        fmc.submit();
+        assert_eq!(fmc.result, Some(FmcResult::Solved { moves: 5 }));
+
+        // running out of time submits the solution written so far
+        fmc.start(parse_moves("R").unwrap());
+        // the changed time limit is kept for the next attempt
+        assert_eq!(fmc.time_left, Duration::from_secs(30 * 60));
+        fmc.start_clock();
+        fmc.solution = "R".to_string();
+        fmc.tick(FMC_TIME_LIMIT);
+        assert_eq!(fmc.result, Some(FmcResult::Dnf));
+        assert_eq!(fmc.time_left, Duration::ZERO);
+    }
+}
@@ -1,14 +1,56 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    window::{PresentMode, WindowMode},
+};
+use bevy_egui::EguiSettings;
+use serde::Serialize;
 
-use crate::ui::UiStates;
+use crate::{
+    persistence,
+    rubiks_cube_plugin::{MAX_CUBE_SIDES, MIN_CUBE_SIDES},
+    ui::UiStates,
+};
+
+const GAME_SETTINGS_FILE: &str = "settings.ron";
+
+// written into the file for future migrations, for now every version is read
+// field by field with defaults for the missing or invalid ones
+const SETTINGS_VERSION: u32 = 1;
+
+pub const WINDOW_MODES: [WindowMode; 4] = [
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::SizedFullscreen,
+    WindowMode::Fullscreen,
+];
+
+pub const RESOLUTIONS: [(u32, u32); 6] = [
+    (1280, 720),
+    (1366, 768),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+    (3840, 2160),
+];
+
+// sample counts every wgpu backend supports
+pub const MSAA_SAMPLES: [u32; 2] = [1, 4];
+
+pub const MIN_UI_SCALE: f64 = 0.5;
+pub const MAX_UI_SCALE: f64 = 3.0;
 
 pub struct GameSettingsPlugin;
 
 impl Plugin for GameSettingsPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(GameSettings::default());
+        let game_settings = GameSettings::load();
+        app.insert_resource(Msaa {
+            samples: game_settings.msaa_samples,
+        });
+        app.insert_resource(game_settings);
         app.add_event::<GameSettingsEvent>();
 
+        app.add_system(apply_initial_settings);
         app.add_system_set(
             SystemSet::on_update(UiStates::Settings).with_system(update_game_settings),
         );
@@ -19,6 +61,18 @@ impl Plugin for GameSettingsPlugin {
 pub struct GameSettings {
     pub mode: WindowMode,
     pub volume: f64,
+    pub cube_size: u32,
+    // duration of the animated quarter turn in seconds
+    pub turn_duration: f32,
+    // window size in windowed and sized fullscreen mode
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+    pub msaa_samples: u32,
+    pub shadows: bool,
+    // scale of the egui windows on top of the window scale factor
+    pub ui_scale: f64,
+    // center orientations count for solving, takes effect with the next cube
+    pub supercube: bool,
 }
 
 impl Default for GameSettings {
@@ -26,28 +80,223 @@ impl Default for GameSettings {
         Self {
             mode: WindowMode::Windowed,
             volume: 2.0,
+            cube_size: 3,
+            turn_duration: 0.15,
+            resolution: (1280, 720),
+            vsync: true,
+            msaa_samples: 4,
+            shadows: true,
+            ui_scale: 1.0,
+            supercube: false,
+        }
+    }
+}
+
+// The file keeps the window mode as a string, ron can not read enum
+// variants without knowing the type, which reading field by field needs.
+#[derive(Serialize)]
+struct SettingsFile {
+    version: u32,
+    mode: String,
+    volume: f64,
+    cube_size: u32,
+    turn_duration: f32,
+    resolution: (u32, u32),
+    vsync: bool,
+    msaa_samples: u32,
+    shadows: bool,
+    ui_scale: f64,
+    supercube: bool,
+}
+
+impl From<&GameSettings> for SettingsFile {
+    fn from(settings: &GameSettings) -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            mode: format!("{:?}", settings.mode),
+            volume: settings.volume,
+            cube_size: settings.cube_size,
+            turn_duration: settings.turn_duration,
+            resolution: settings.resolution,
+            vsync: settings.vsync,
+            msaa_samples: settings.msaa_samples,
+            shadows: settings.shadows,
+            ui_scale: settings.ui_scale,
+            supercube: settings.supercube,
+        }
+    }
+}
+
+impl GameSettings {
+    pub fn load() -> Self {
+        persistence::load::<ron::Value>(GAME_SETTINGS_FILE)
+            .map_or_else(Self::default, |value| Self::from_value(&value))
+    }
+
+    /// Settings of a parsed file, missing and invalid fields keep their
+    /// default so one broken value does not reset everything.
+    pub fn from_value(value: &ron::Value) -> Self {
+        let defaults = Self::default();
+        let ron::Value::Map(map) = value else {
+            return defaults;
+        };
+        let field = |name: &str| {
+            map.iter()
+                .find(|(key, _)| **key == ron::Value::String(name.to_string()))
+                .map(|(_, value)| value.clone())
+        };
+
+        let version = field("version").and_then(|v| v.into_rust::<u32>().ok());
+        if version.is_some_and(|version| version > SETTINGS_VERSION) {
+            warn!("{GAME_SETTINGS_FILE} is from a newer version, unknown settings are ignored");
+        }
+
+        let mode = field("mode")
+            .and_then(|v| v.into_rust::<String>().ok())
+            .and_then(|name| {
+                WINDOW_MODES
+                    .into_iter()
+                    .find(|mode| format!("{mode:?}") == name)
+            });
+        let volume = field("volume")
+            .and_then(|v| v.into_rust::<f64>().ok())
+            .filter(|volume| (0.0..=10.0).contains(volume));
+        let cube_size = field("cube_size")
+            .and_then(|v| v.into_rust::<u32>().ok())
+            .filter(|size| (MIN_CUBE_SIDES..=MAX_CUBE_SIDES).contains(size));
+        let turn_duration = field("turn_duration")
+            .and_then(|v| v.into_rust::<f32>().ok())
+            .filter(|duration| (0.0..=2.0).contains(duration));
+        let resolution = field("resolution")
+            .and_then(|v| v.into_rust::<(u32, u32)>().ok())
+            .filter(|&(width, height)| width >= 320 && height >= 240);
+        let vsync = field("vsync").and_then(|v| v.into_rust::<bool>().ok());
+        let msaa_samples = field("msaa_samples")
+            .and_then(|v| v.into_rust::<u32>().ok())
+            .filter(|samples| MSAA_SAMPLES.contains(samples));
+        let shadows = field("shadows").and_then(|v| v.into_rust::<bool>().ok());
+        let ui_scale = field("ui_scale")
+            .and_then(|v| v.into_rust::<f64>().ok())
+            .filter(|scale| (MIN_UI_SCALE..=MAX_UI_SCALE).contains(scale));
+        let supercube = field("supercube").and_then(|v| v.into_rust::<bool>().ok());
+        Self {
+            mode: mode.unwrap_or(defaults.mode),
+            volume: volume.unwrap_or(defaults.volume),
+            cube_size: cube_size.unwrap_or(defaults.cube_size),
+            turn_duration: turn_duration.unwrap_or(defaults.turn_duration),
+            resolution: resolution.unwrap_or(defaults.resolution),
+            vsync: vsync.unwrap_or(defaults.vsync),
+            msaa_samples: msaa_samples.unwrap_or(defaults.msaa_samples),
+            shadows: shadows.unwrap_or(defaults.shadows),
+            ui_scale: ui_scale.unwrap_or(defaults.ui_scale),
+            supercube: supercube.unwrap_or(defaults.supercube),
         }
     }
+
+    pub fn save(&self) {
+        persistence::save(GAME_SETTINGS_FILE, &SettingsFile::from(self));
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    fn apply_to_window(&self, window: &mut Window) {
+        window.set_mode(self.mode);
+        let (width, height) = self.resolution;
+        window.set_resolution(width as f32, height as f32);
+        window.set_present_mode(self.present_mode());
+    }
 }
 
 pub enum GameSettingsEvent {
     Apply(GameSettings),
 }
 
+// the window is created after the settings are loaded, the lights use the
+// settings when spawned
+fn apply_initial_settings(
+    game_settings: Res<GameSettings>,
+    mut windows: ResMut<Windows>,
+    mut egui_settings: ResMut<EguiSettings>,
+    mut done: Local<bool>,
+) {
+    if *done {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        game_settings.apply_to_window(window);
+        egui_settings.scale_factor = game_settings.ui_scale;
+        *done = true;
+    }
+}
+
 fn update_game_settings(
     mut windows: ResMut<Windows>,
+    mut msaa: ResMut<Msaa>,
+    mut egui_settings: ResMut<EguiSettings>,
+    mut lights: Query<&mut PointLight>,
     mut game_settings: ResMut<GameSettings>,
     mut game_settings_events: EventReader<GameSettingsEvent>,
 ) {
     for event in game_settings_events.iter() {
         match event {
             GameSettingsEvent::Apply(new_settings) => {
-                windows
-                    .get_primary_mut()
-                    .unwrap()
-                    .set_mode(new_settings.mode);
+                new_settings.apply_to_window(windows.get_primary_mut().unwrap());
+                msaa.samples = new_settings.msaa_samples;
+                egui_settings.scale_factor = new_settings.ui_scale;
+                for mut light in &mut lights {
+                    light.shadows_enabled = new_settings.shadows;
+                }
                 *game_settings = *new_settings;
+                game_settings.save();
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_ron(s: &str) -> GameSettings {
+        GameSettings::from_value(&ron::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn settings_file() {
+        let settings = GameSettings {
+            mode: WindowMode::BorderlessFullscreen,
+            volume: 4.5,
+            cube_size: 5,
+            turn_duration: 0.3,
+            resolution: (1920, 1080),
+            vsync: false,
+            msaa_samples: 1,
+            shadows: false,
+            ui_scale: 1.5,
+            supercube: true,
+        };
+        let file = SettingsFile::from(&settings);
+        assert_eq!(from_ron(&ron::to_string(&file).unwrap()), settings);
+
+        // broken and missing fields fall back to their defaults
+        let settings = from_ron(
+            r#"(version: 1, mode: "Maximized", volume: 3.0, cube_size: 99, msaa_samples: 3, shadows: false)"#,
+        );
+        assert_eq!(
+            settings,
+            GameSettings {
+                volume: 3.0,
+                shadows: false,
+                ..default()
+            }
+        );
+        assert_eq!(from_ron("(volume: true)"), GameSettings::default());
+        assert_eq!(from_ron("[1, 2]"), GameSettings::default());
+    }
+}
@@ -1,6 +1,12 @@
 use bevy::prelude::*;
 
-use crate::{rubiks_cube::RubiksCube, GameStates};
+use crate::{
+    blindfolded::Blindfolded,
+    rubiks_cube::RubiksCube,
+    rubiks_cube_plugin::{MoveQueue, TurnCommitted, TurnSource},
+    solve_timer::SolveTimer,
+    GameStates,
+};
 
 pub struct GameStatePlugin;
 
@@ -8,13 +14,23 @@ impl Plugin for GameStatePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GameState::default());
 
-        app.add_system_set(SystemSet::on_update(GameStates::InGame).with_system(update_game_state));
+        app.add_system_set(
+            SystemSet::on_update(GameStates::InGame)
+                .with_system(update_game_state)
+                .with_system(update_solve_timer.after(update_game_state)),
+        );
+        app.add_system_set(SystemSet::on_exit(GameStates::InGame).with_system(reset_game_state));
     }
 }
 
+/// Systems that store a finished solve, they run once the timer is stopped.
+#[derive(SystemLabel)]
+pub struct RecordSolve;
+
 #[derive(Debug, Default, Resource)]
 pub struct GameState {
     pub is_solved: bool,
+    pub timer: SolveTimer,
 }
 
 fn update_game_state(rubiks_cube: Query<&RubiksCube>, mut game_state: ResMut<GameState>) {
@@ -22,3 +38,44 @@ fn update_game_state(rubiks_cube: Query<&RubiksCube>, mut game_state: ResMut<Gam
         game_state.is_solved = rb.is_solved();
     }
 }
+
+pub fn update_solve_timer(
+    time: Res<Time>,
+    move_queue: Res<MoveQueue>,
+    blindfolded: Res<Blindfolded>,
+    rubiks_cube: Query<&RubiksCube>,
+    mut turn_events: EventReader<TurnCommitted>,
+    mut game_state: ResMut<GameState>,
+    mut scrambling: Local<bool>,
+) {
+    let Ok(rb) = rubiks_cube.get_single() else {
+        return;
+    };
+    let timer = &mut game_state.timer;
+    timer.tick(time.delta());
+    for TurnCommitted(queued) in turn_events.iter() {
+        match queued.source {
+            TurnSource::Scramble => {
+                *timer = SolveTimer::Idle;
+                *scrambling = true;
+            }
+            TurnSource::Player => timer.turn(queued.turn.is_rotation(rb.side_size)),
+            TurnSource::Undo | TurnSource::Redo | TurnSource::Replay => {}
+        }
+    }
+    // the inspection starts once the whole scramble is shown,
+    // blindfolded solves are timed on their own
+    if *scrambling && move_queue.is_empty() {
+        if !blindfolded.enabled {
+            timer.start_inspection();
+        }
+        *scrambling = false;
+    }
+    if game_state.is_solved {
+        game_state.timer.solved();
+    }
+}
+
+pub fn reset_game_state(mut game_state: ResMut<GameState>) {
+    *game_state = GameState::default();
+}
@@ -0,0 +1,281 @@
+use std::{fmt::Display, marker::PhantomData};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{keyboard::KEYBOARD_TURNS, notation::Move, persistence};
+
+const INPUT_BINDINGS_FILE: &str = "input_bindings.ron";
+
+pub struct InputBindingsPlugin;
+
+impl Plugin for InputBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_input_bindings());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// Button with the modifiers that have to be held when it is pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Binding {
+    pub button: InputButton,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool,
+}
+
+impl Binding {
+    pub fn key(key: KeyCode) -> Self {
+        Self {
+            button: InputButton::Key(key),
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self {
+            button: InputButton::Mouse(button),
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    pub fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    fn modifiers_match(&self, keys: &Input<KeyCode>) -> bool {
+        self.ctrl == keys.any_pressed([KeyCode::LControl, KeyCode::RControl])
+            && self.shift == keys.any_pressed([KeyCode::LShift, KeyCode::RShift])
+    }
+
+    // modifiers are only checked when the button goes down, so letting go
+    // of them does not interrupt a drag
+    pub fn pressed(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        match self.button {
+            InputButton::Key(key) => keys.pressed(key),
+            InputButton::Mouse(button) => mouse.pressed(button),
+        }
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        self.modifiers_match(keys)
+            && match self.button {
+                InputButton::Key(key) => keys.just_pressed(key),
+                InputButton::Mouse(button) => mouse.just_pressed(button),
+            }
+    }
+
+    pub fn just_released(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        match self.button {
+            InputButton::Key(key) => keys.just_released(key),
+            InputButton::Mouse(button) => mouse.just_released(button),
+        }
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        match self.button {
+            InputButton::Key(key) => write!(f, "{key:?}"),
+            InputButton::Mouse(MouseButton::Other(button)) => write!(f, "Mouse {button}"),
+            InputButton::Mouse(button) => write!(f, "Mouse {button:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    // drag a layer of the cube
+    TurnLayer,
+    OrbitCamera,
+    Pause,
+    Undo,
+    Redo,
+    Turn(Move),
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::TurnLayer => write!(f, "Drag layer"),
+            Action::OrbitCamera => write!(f, "Orbit camera"),
+            Action::Pause => write!(f, "Pause"),
+            Action::Undo => write!(f, "Undo"),
+            Action::Redo => write!(f, "Redo"),
+            Action::Turn(m) => write!(f, "Turn {m}"),
+        }
+    }
+}
+
+/// All bindings of the game. An action can have several bindings
+/// but a binding should trigger only one action.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings(pub Vec<(Action, Binding)>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let mut bindings = vec![
+            (Action::TurnLayer, Binding::mouse(MouseButton::Left)),
+            (Action::OrbitCamera, Binding::mouse(MouseButton::Right)),
+            (Action::Pause, Binding::key(KeyCode::Escape)),
+            (Action::Undo, Binding::key(KeyCode::Z).with_ctrl()),
+            (
+                Action::Redo,
+                Binding::key(KeyCode::Z).with_ctrl().with_shift(),
+            ),
+            (Action::Redo, Binding::key(KeyCode::Y).with_ctrl()),
+        ];
+        bindings.extend(KEYBOARD_TURNS.iter().map(|(key, notation)| {
+            let m = notation
+                .parse()
+                .expect("Keyboard turns should be valid notation");
+            (Action::Turn(m), Binding::key(*key))
+        }));
+        Self(bindings)
+    }
+}
+
+impl InputBindings {
+    pub fn bindings(&self, action: Action) -> impl Iterator<Item = &Binding> {
+        self.0
+            .iter()
+            .filter(move |(a, _)| *a == action)
+            .map(|(_, binding)| binding)
+    }
+
+    /// Index of another entry that uses the same binding as the entry at `index`.
+    pub fn conflict(&self, index: usize) -> Option<usize> {
+        let binding = self.0[index].1;
+        (0..self.0.len()).find(|&i| i != index && self.0[i].1 == binding)
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        (0..self.0.len()).any(|i| self.conflict(i).is_some())
+    }
+
+    pub fn save(&self) {
+        persistence::save(INPUT_BINDINGS_FILE, self);
+    }
+}
+
+fn load_input_bindings() -> InputBindings {
+    match persistence::load::<InputBindings>(INPUT_BINDINGS_FILE) {
+        Some(bindings) if !bindings.has_conflicts() => bindings,
+        Some(_) => {
+            warn!("Saved input bindings have conflicts, using the default ones");
+            InputBindings::default()
+        }
+        None => InputBindings::default(),
+    }
+}
+
+/// Checks actions against the current bindings.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    bindings: Res<'w, InputBindings>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ActionInput<'w, 's> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.bindings
+            .bindings(action)
+            .any(|b| b.pressed(&self.keys, &self.mouse))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .bindings(action)
+            .any(|b| b.just_pressed(&self.keys, &self.mouse))
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.bindings
+            .bindings(action)
+            .any(|b| b.just_released(&self.keys, &self.mouse))
+    }
+
+    /// Moves of the turn bindings pressed this frame.
+    pub fn just_pressed_turns(&self) -> impl Iterator<Item = Move> + '_ {
+        self.bindings
+            .0
+            .iter()
+            .filter_map(|(action, binding)| match action {
+                Action::Turn(m) if binding.just_pressed(&self.keys, &self.mouse) => Some(*m),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_have_no_conflicts() {
+        let bindings = InputBindings::default();
+        assert!(!bindings.has_conflicts());
+        assert_eq!(bindings.bindings(Action::Redo).count(), 2);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut bindings = InputBindings::default();
+        bindings.0[1].1 = Binding::mouse(MouseButton::Left);
+        assert_eq!(bindings.conflict(0), Some(1));
+        assert_eq!(bindings.conflict(1), Some(0));
+        assert_eq!(bindings.conflict(2), None);
+        assert!(bindings.has_conflicts());
+
+        // modifiers make a different binding
+        bindings.0[1].1 = Binding::mouse(MouseButton::Left).with_shift();
+        assert!(!bindings.has_conflicts());
+    }
+
+    #[test]
+    fn bindings_ron() {
+        let bindings = InputBindings::default();
+        let s = ron::to_string(&bindings).unwrap();
+        assert_eq!(ron::from_str::<InputBindings>(&s).unwrap(), bindings);
+
+        // modifiers can be left out
+        let bindings: InputBindings = ron::from_str(
+            r#"([(Turn("R'"), (button: Key(K))), (Undo, (button: Key(Z), ctrl: true))])"#,
+        )
+        .unwrap();
+        assert_eq!(
+            bindings.0,
+            vec![
+                (
+                    Action::Turn("R'".parse().unwrap()),
+                    Binding::key(KeyCode::K)
+                ),
+                (Action::Undo, Binding::key(KeyCode::Z).with_ctrl()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+
+use crate::{input_bindings::ActionInput, rubiks_cube_plugin::RubiksCubeEvent, GameStates};
+
+/// Default key layout used by most online timers, e.g. csTimer.
+pub const KEYBOARD_TURNS: [(KeyCode, &str); 34] = [
+    (KeyCode::I, "R"),
+    (KeyCode::K, "R'"),
+    (KeyCode::J, "U"),
+    (KeyCode::F, "U'"),
+    (KeyCode::H, "F"),
+    (KeyCode::G, "F'"),
+    (KeyCode::D, "L"),
+    (KeyCode::E, "L'"),
+    (KeyCode::S, "D"),
+    (KeyCode::L, "D'"),
+    (KeyCode::W, "B"),
+    (KeyCode::O, "B'"),
+    (KeyCode::U, "r"),
+    (KeyCode::M, "r'"),
+    (KeyCode::V, "l"),
+    (KeyCode::R, "l'"),
+    (KeyCode::C, "u'"),
+    (KeyCode::Comma, "u"),
+    (KeyCode::Z, "d"),
+    (KeyCode::Slash, "d'"),
+    (KeyCode::Key5, "M"),
+    (KeyCode::Key6, "M"),
+    (KeyCode::X, "M'"),
+    (KeyCode::Period, "M'"),
+    (KeyCode::T, "x"),
+    (KeyCode::Y, "x"),
+    (KeyCode::B, "x'"),
+    (KeyCode::N, "x'"),
+    (KeyCode::Semicolon, "y"),
+    (KeyCode::A, "y'"),
+    (KeyCode::P, "z"),
+    (KeyCode::Q, "z'"),
+    (KeyCode::Key2, "S"),
+    (KeyCode::Key3, "S'"),
+];
+
+pub struct KeyboardTurnsPlugin;
+
+impl Plugin for KeyboardTurnsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameStates::InGame).with_system(keyboard_turns));
+        app.add_system_set(SystemSet::on_update(GameStates::Trainer).with_system(keyboard_turns));
+        app.add_system_set(SystemSet::on_update(GameStates::Fmc).with_system(keyboard_turns));
+    }
+}
+
+fn keyboard_turns(
+    action_input: ActionInput,
+    mut egui_context: ResMut<EguiContext>,
+    mut rubiks_cube_events: EventWriter<RubiksCubeEvent>,
+) {
+    // typing into text fields should not turn the cube
+    if egui_context.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    for m in action_input.just_pressed_turns() {
+        rubiks_cube_events.send(RubiksCubeEvent::Turn(m));
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    cases::{f2l_solved, last_layer_colors, top_views, Case, CaseKind, Cases, AUFS},
+    notation::{parse_moves, Move},
+    rubiks_cube::RubiksCube,
+    GameStates,
+};
+
+pub struct LastLayerPlugin;
+
+impl Plugin for LastLayerPlugin {
+    fn build(&self, app: &mut App) {
+        let cases = Cases::load();
+        app.insert_resource(CaseRecognizer::new(&cases));
+        app.insert_resource(LastLayerCase::default());
+        app.insert_resource(cases);
+
+        app.add_system_set(
+            SystemSet::on_update(GameStates::InGame).with_system(recognize_last_layer),
+        );
+    }
+}
+
+/// Case showing on the last layer and the moves that set it up for the algorithm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recognition {
+    pub case: Case,
+    // rotation that brings the last layer on top followed by the AUF
+    pub setup: Vec<Move>,
+}
+
+/// Case of the in-game cube, updated after each turn.
+#[derive(Resource, Debug, Default)]
+pub struct LastLayerCase(pub Option<Recognition>);
+
+/// Recognizes OLL and PLL cases of a 3x3x3 from the stickers of the last
+/// layer. The patterns are generated by undoing each algorithm on a solved
+/// cube, so only the algorithms have to be listed.
+#[derive(Resource, Debug)]
+pub struct CaseRecognizer {
+    cases: Vec<Case>,
+    // index of the case and of the AUF for each pattern
+    oll: HashMap<Vec<bool>, (usize, usize)>,
+    pll: HashMap<Vec<IVec3>, (usize, usize)>,
+}
+
+impl CaseRecognizer {
+    pub fn new(cases: &Cases) -> Self {
+        let mut oll = HashMap::new();
+        for (index, case) in cases.of_kind(CaseKind::Oll) {
+            for (auf, rb) in case.states().into_iter().enumerate() {
+                oll.entry(oll_pattern(&rb)).or_insert((index, auf % 4));
+            }
+        }
+        let mut pll = HashMap::new();
+        for (index, case) in cases.of_kind(CaseKind::Pll) {
+            for (auf, rb) in case.states().into_iter().enumerate() {
+                pll.entry(last_layer_colors(&rb))
+                    .or_insert((index, auf % 4));
+            }
+        }
+        Self {
+            cases: cases.0.clone(),
+            oll,
+            pll,
+        }
+    }
+
+    /// Finds the case of a 3x3x3 with the first two layers solved, with any
+    /// color on top. Solved last layers and other cubes give `None`.
+    pub fn recognize(&self, rb: &RubiksCube) -> Option<Recognition> {
+        if rb.side_size != 3 {
+            return None;
+        }
+        let (mut setup, rb) = top_views(rb).find(|(_, rb)| f2l_solved(rb))?;
+        let (index, auf) = if rb.face_oriented(IVec3::Y) {
+            self.pll.get(&last_layer_colors(&rb))?
+        } else {
+            self.oll.get(&oll_pattern(&rb))?
+        };
+        setup.extend(parse_moves(AUFS[*auf]).unwrap());
+        Some(Recognition {
+            case: self.cases[*index].clone(),
+            setup,
+        })
+    }
+}
+
+// which top layer stickers show the top color
+fn oll_pattern(rb: &RubiksCube) -> Vec<bool> {
+    last_layer_colors(rb)
+        .into_iter()
+        .map(|color| color == IVec3::Y)
+        .collect()
+}
+
+fn recognize_last_layer(
+    case_recognizer: Res<CaseRecognizer>,
+    rubiks_cube: Query<&RubiksCube, Changed<RubiksCube>>,
+    mut last_layer_case: ResMut<LastLayerCase>,
+) {
+    if let Ok(rb) = rubiks_cube.get_single() {
+        last_layer_case.0 = case_recognizer.recognize(rb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cases::step_solved,
+        notation::{format_moves, moves_layer_turns},
+    };
+
+    fn apply(rb: &mut RubiksCube, moves: &str) {
+        for turn in moves_layer_turns(&parse_moves(moves).unwrap(), 3) {
+            rb.turn(&turn);
+        }
+    }
+
+    fn solve(rb: &mut RubiksCube, recognition: &Recognition) {
+        apply(rb, &format_moves(&recognition.setup));
+        apply(rb, &recognition.case.algorithm);
+    }
+
+    #[test]
+    fn recognize_cases() {
+        let recognizer = CaseRecognizer::new(&Cases::load());
+
+        // undoing the sune shows the sune, white on top
+        let mut rb = RubiksCube::new(3);
+        apply(&mut rb, "R U2 R' U' R U' R' U' x2");
+        let recognition = recognizer.recognize(&rb).unwrap();
+        assert_eq!(recognition.case.name, "OLL 27 (Sune)");
+        solve(&mut rb, &recognition);
+        assert!(step_solved(&rb, CaseKind::Oll));
+
+        let mut rb = RubiksCube::new(3);
+        apply(&mut rb, "U R U R' U' R' F R2 U' R' U' R U R' F' z");
+        let recognition = recognizer.recognize(&rb).unwrap();
+        assert_eq!(recognition.case.name, "T perm");
+        solve(&mut rb, &recognition);
+        assert!(step_solved(&rb, CaseKind::Pll));
+
+        let mut rb = RubiksCube::new(3);
+        assert_eq!(recognizer.recognize(&rb), None);
+        apply(&mut rb, "R U R'");
+        assert_eq!(recognizer.recognize(&rb), None);
+        assert_eq!(recognizer.recognize(&RubiksCube::new(4)), None);
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    persistence,
+    rubiks_cube::{RubiksCube, FACE_NORMALS},
+};
+
+const LETTER_SCHEME_FILE: &str = "letter_scheme.ron";
+
+const SPEFFZ: &str = "ABCDEFGHIJKLMNOPQRSTUVWX";
+
+// (face, piece) of each corner sticker in the Speffz order: the faces U, L,
+// F, R, B, D, each clockwise from the top left corner with U on top, seen
+// from the B face for U and from the F face for D
+const CORNER_STICKERS: [(IVec3, IVec3); 24] = [
+    (IVec3::Y, IVec3::new(-1, 1, -1)),
+    (IVec3::Y, IVec3::new(1, 1, -1)),
+    (IVec3::Y, IVec3::new(1, 1, 1)),
+    (IVec3::Y, IVec3::new(-1, 1, 1)),
+    (IVec3::NEG_X, IVec3::new(-1, 1, -1)),
+    (IVec3::NEG_X, IVec3::new(-1, 1, 1)),
+    (IVec3::NEG_X, IVec3::new(-1, -1, 1)),
+    (IVec3::NEG_X, IVec3::new(-1, -1, -1)),
+    (IVec3::Z, IVec3::new(-1, 1, 1)),
+    (IVec3::Z, IVec3::new(1, 1, 1)),
+    (IVec3::Z, IVec3::new(1, -1, 1)),
+    (IVec3::Z, IVec3::new(-1, -1, 1)),
+    (IVec3::X, IVec3::new(1, 1, 1)),
+    (IVec3::X, IVec3::new(1, 1, -1)),
+    (IVec3::X, IVec3::new(1, -1, -1)),
+    (IVec3::X, IVec3::new(1, -1, 1)),
+    (IVec3::NEG_Z, IVec3::new(1, 1, -1)),
+    (IVec3::NEG_Z, IVec3::new(-1, 1, -1)),
+    (IVec3::NEG_Z, IVec3::new(-1, -1, -1)),
+    (IVec3::NEG_Z, IVec3::new(1, -1, -1)),
+    (IVec3::NEG_Y, IVec3::new(-1, -1, 1)),
+    (IVec3::NEG_Y, IVec3::new(1, -1, 1)),
+    (IVec3::NEG_Y, IVec3::new(1, -1, -1)),
+    (IVec3::NEG_Y, IVec3::new(-1, -1, -1)),
+];
+
+// edge stickers in the same order, clockwise from the top edge
+const EDGE_STICKERS: [(IVec3, IVec3); 24] = [
+    (IVec3::Y, IVec3::new(0, 1, -1)),
+    (IVec3::Y, IVec3::new(1, 1, 0)),
+    (IVec3::Y, IVec3::new(0, 1, 1)),
+    (IVec3::Y, IVec3::new(-1, 1, 0)),
+    (IVec3::NEG_X, IVec3::new(-1, 1, 0)),
+    (IVec3::NEG_X, IVec3::new(-1, 0, 1)),
+    (IVec3::NEG_X, IVec3::new(-1, -1, 0)),
+    (IVec3::NEG_X, IVec3::new(-1, 0, -1)),
+    (IVec3::Z, IVec3::new(0, 1, 1)),
+    (IVec3::Z, IVec3::new(1, 0, 1)),
+    (IVec3::Z, IVec3::new(0, -1, 1)),
+    (IVec3::Z, IVec3::new(-1, 0, 1)),
+    (IVec3::X, IVec3::new(1, 1, 0)),
+    (IVec3::X, IVec3::new(1, 0, -1)),
+    (IVec3::X, IVec3::new(1, -1, 0)),
+    (IVec3::X, IVec3::new(1, 0, 1)),
+    (IVec3::NEG_Z, IVec3::new(0, 1, -1)),
+    (IVec3::NEG_Z, IVec3::new(-1, 0, -1)),
+    (IVec3::NEG_Z, IVec3::new(0, -1, -1)),
+    (IVec3::NEG_Z, IVec3::new(1, 0, -1)),
+    (IVec3::NEG_Y, IVec3::new(0, -1, 1)),
+    (IVec3::NEG_Y, IVec3::new(1, -1, 0)),
+    (IVec3::NEG_Y, IVec3::new(0, -1, -1)),
+    (IVec3::NEG_Y, IVec3::new(-1, -1, 0)),
+];
+
+// Old Pochmann buffer for corners, the U sticker of UBL
+const CORNER_BUFFER: usize = 0;
+// M2 buffer for edges, the D sticker of DF
+const EDGE_BUFFER: usize = 20;
+
+pub struct LettersPlugin;
+
+impl Plugin for LettersPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(
+            persistence::load::<LetterScheme>(LETTER_SCHEME_FILE).unwrap_or_default(),
+        );
+    }
+}
+
+/// Letters of the corner and edge stickers of a 3x3x3 in the Speffz order,
+/// the default is Speffz itself.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LetterScheme {
+    // letters are drawn on the stickers
+    pub show: bool,
+    pub corners: String,
+    pub edges: String,
+}
+
+impl Default for LetterScheme {
+    fn default() -> Self {
+        Self {
+            show: false,
+            corners: SPEFFZ.to_string(),
+            edges: SPEFFZ.to_string(),
+        }
+    }
+}
+
+impl LetterScheme {
+    // a letter for each of the 24 stickers
+    pub fn is_valid(&self) -> bool {
+        self.corners.chars().count() == 24 && self.edges.chars().count() == 24
+    }
+
+    /// Letter of the sticker at its home position, `None` for centers and
+    /// for cubes other than the 3x3x3.
+    pub fn sticker_letter(&self, normal: IVec3, offset: IVec3) -> Option<char> {
+        let (stickers, letters) = match offset.abs().to_array().iter().sum() {
+            3 => (&CORNER_STICKERS, &self.corners),
+            2 => (&EDGE_STICKERS, &self.edges),
+            _ => return None,
+        };
+        let index = stickers.iter().position(|s| *s == (normal, offset))?;
+        letters.chars().nth(index)
+    }
+
+    pub fn save(&self) {
+        persistence::save(LETTER_SCHEME_FILE, self);
+    }
+}
+
+/// Targets of a blindfolded solve, traced from the buffers. Cycle breaks
+/// start at the first unsolved piece in letter order, pieces twisted or
+/// flipped in place are shot to and back.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Memo {
+    pub edges: Vec<char>,
+    pub corners: Vec<char>,
+}
+
+impl Memo {
+    /// An odd number of edge targets needs a parity algorithm.
+    pub fn parity(&self) -> bool {
+        self.edges.len() % 2 == 1
+    }
+}
+
+/// Letter pairs separated by spaces, e.g. "AB CD E".
+pub fn letter_pairs(letters: &[char]) -> String {
+    letters
+        .chunks(2)
+        .map(|pair| pair.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Display for Memo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Edges: {}\nCorners: {}",
+            letter_pairs(&self.edges),
+            letter_pairs(&self.corners)
+        )
+    }
+}
+
+/// Traces the memo of a 3x3x3 relative to its centers, so whole cube
+/// rotations do not change it. Edges use the M2 buffer and corners the Old
+/// Pochmann buffer. Fails for other sizes and for invalid schemes.
+pub fn trace_memo(rb: &RubiksCube, scheme: &LetterScheme) -> Option<Memo> {
+    if rb.side_size != 3 || !scheme.is_valid() {
+        return None;
+    }
+    let edges = trace(rb, &EDGE_STICKERS, EDGE_BUFFER);
+    let corners = trace(rb, &CORNER_STICKERS, CORNER_BUFFER);
+    let letters = |indices: Vec<usize>, letters: &str| {
+        let letters: Vec<char> = letters.chars().collect();
+        indices.into_iter().map(|i| letters[i]).collect()
+    };
+    Some(Memo {
+        edges: letters(edges, &scheme.edges),
+        corners: letters(corners, &scheme.corners),
+    })
+}
+
+// indices of the stickers to shoot to, in the order of the solve
+fn trace(rb: &RubiksCube, stickers: &[(IVec3, IVec3); 24], buffer: usize) -> Vec<usize> {
+    // the sticker that belongs at each location, stickers of a piece share
+    // the offset
+    let targets: Vec<usize> = stickers
+        .iter()
+        .map(|location| {
+            let home = home_location(rb, *location);
+            stickers.iter().position(|s| *s == home).unwrap()
+        })
+        .collect();
+    let piece = |index: usize| stickers[index].1;
+    let solved = |offset: IVec3| {
+        (0..24)
+            .filter(|i| piece(*i) == offset)
+            .all(|i| targets[i] == i)
+    };
+
+    let mut visited = vec![piece(buffer)];
+    let mut letters = Vec::new();
+    let mut current = targets[buffer];
+    while piece(current) != piece(buffer) {
+        letters.push(current);
+        visited.push(piece(current));
+        current = targets[current];
+    }
+    while let Some(start) = (0..24).find(|i| !visited.contains(&piece(*i)) && !solved(piece(*i))) {
+        letters.push(start);
+        visited.push(piece(start));
+        current = targets[start];
+        while piece(current) != piece(start) {
+            letters.push(current);
+            visited.push(piece(current));
+            current = targets[current];
+        }
+        letters.push(current);
+    }
+    letters
+}
+
+// home (face, piece) of the sticker at the location, both relative to the
+// centers
+fn home_location(rb: &RubiksCube, (normal, offset): (IVec3, IVec3)) -> (IVec3, IVec3) {
+    // centers give the rotation of the whole cube
+    let to_cube = |v: IVec3| -> IVec3 {
+        FACE_NORMALS
+            .into_iter()
+            .filter(|face| face.dot(v) > 0)
+            .map(|face| {
+                FACE_NORMALS
+                    .into_iter()
+                    .find(|center| rb.center_sticker(*center) == face)
+                    .unwrap()
+            })
+            .sum()
+    };
+    let pos = rb.offset_pos(to_cube(offset));
+    let home = rb.centered_coords(rb.pos_to_cube[pos as usize].1 as u32) / 2;
+    (rb.sticker(pos, to_cube(normal)), home)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{moves_layer_turns, parse_moves};
+
+    fn scrambled(moves: &str) -> RubiksCube {
+        let mut rb = RubiksCube::new(3);
+        for turn in moves_layer_turns(&parse_moves(moves).unwrap(), 3) {
+            rb.turn(&turn);
+        }
+        rb
+    }
+
+    #[test]
+    fn sticker_letters() {
+        let scheme = LetterScheme::default();
+        assert!(scheme.is_valid());
+        assert_eq!(
+            scheme.sticker_letter(IVec3::Y, IVec3::new(-1, 1, -1)),
+            Some('A')
+        );
+        assert_eq!(
+            scheme.sticker_letter(IVec3::NEG_Y, IVec3::new(0, -1, 1)),
+            Some('U')
+        );
+        assert_eq!(
+            scheme.sticker_letter(IVec3::X, IVec3::new(1, 0, -1)),
+            Some('N')
+        );
+        assert_eq!(scheme.sticker_letter(IVec3::X, IVec3::X), None);
+        // every sticker of the corners and edges has a letter
+        for stickers in [CORNER_STICKERS, EDGE_STICKERS] {
+            for (normal, offset) in stickers {
+                assert_eq!(normal.dot(offset), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn trace_cycles() {
+        let scheme = LetterScheme::default();
+        assert_eq!(
+            trace_memo(&RubiksCube::new(3), &scheme),
+            Some(Memo::default())
+        );
+
+        // the solved edge buffer needs a cycle break, the corner buffer is
+        // part of the cycle
+        let memo = trace_memo(&scrambled("U"), &scheme).unwrap();
+        assert_eq!(letter_pairs(&memo.edges), "AD CB A");
+        assert_eq!(letter_pairs(&memo.corners), "DC B");
+        assert!(memo.parity());
+        assert_eq!(trace_memo(&scrambled("U x y2"), &scheme), Some(memo));
+
+        // two swaps, the edges need two cycle breaks
+        let memo = trace_memo(&scrambled("U2"), &scheme).unwrap();
+        assert_eq!(letter_pairs(&memo.edges), "AC AB DB");
+        assert_eq!(letter_pairs(&memo.corners), "CB DB");
+        assert!(!memo.parity());
+
+        assert_eq!(trace_memo(&RubiksCube::new(4), &scheme), None);
+        let scheme = LetterScheme {
+            edges: "ABC".to_string(),
+            ..scheme
+        };
+        assert_eq!(trace_memo(&RubiksCube::new(3), &scheme), None);
+    }
+}
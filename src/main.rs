@@ -2,23 +2,54 @@
 use bevy::prelude::*;
 
 mod audio;
+mod blindfolded;
 mod camera;
+mod cases;
+mod cfop;
+mod color_scheme;
+mod cube_instancing;
 mod cube_material;
 mod cursor;
+mod end_game;
+mod fmc;
 mod game_settings;
 mod game_state;
+mod input_bindings;
+mod keyboard;
+mod last_layer;
+mod letters;
+mod notation;
+mod persistence;
 mod ray_extension;
+mod replay;
 mod rubiks_cube;
 mod rubiks_cube_plugin;
+mod save_game;
+mod solve_timer;
+mod statistics;
+mod trainer;
 mod ui;
+mod verify;
 
 use audio::AudioPlugin;
+use blindfolded::BlindfoldedPlugin;
 use camera::{CameraControllerPlugin, OrbitCamera};
-use cube_material::CubeMaterial;
+use color_scheme::ColorSchemePlugin;
+use cube_instancing::CubeInstancingPlugin;
 use cursor::CursorRayPlugin;
-use game_settings::GameSettingsPlugin;
+use end_game::EndGamePlugin;
+use fmc::FmcPlugin;
+use game_settings::{GameSettings, GameSettingsPlugin};
 use game_state::GameStatePlugin;
+use input_bindings::InputBindingsPlugin;
+use keyboard::KeyboardTurnsPlugin;
+use last_layer::LastLayerPlugin;
+use letters::LettersPlugin;
+use replay::ReplayPlugin;
 use rubiks_cube_plugin::RubiksCubePlugin;
+use save_game::SaveGamePlugin;
+use statistics::StatisticsPlugin;
+use trainer::TrainerPlugin;
 use ui::UiPlugin;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -27,9 +58,20 @@ pub enum GameStates {
     InGame,
     Paused,
     EndGame,
+    Replay,
+    Trainer,
+    Fmc,
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, command, path] = args.as_slice() {
+        if command == "verify" {
+            verify_replay(path);
+            return;
+        }
+    }
+
     let mut app = App::new();
 
     app.insert_resource(ClearColor(Color::BLACK));
@@ -43,12 +85,24 @@ fn main() {
     app.add_state(GameStates::MainMenu);
 
     app.add_plugin(AudioPlugin);
+    app.add_plugin(BlindfoldedPlugin);
     app.add_plugin(CameraControllerPlugin);
-    app.add_plugin(MaterialPlugin::<CubeMaterial>::default());
+    app.add_plugin(ColorSchemePlugin);
+    app.add_plugin(CubeInstancingPlugin);
     app.add_plugin(CursorRayPlugin);
+    app.add_plugin(EndGamePlugin);
+    app.add_plugin(FmcPlugin);
     app.add_plugin(GameSettingsPlugin);
     app.add_plugin(GameStatePlugin);
+    app.add_plugin(InputBindingsPlugin);
+    app.add_plugin(KeyboardTurnsPlugin);
+    app.add_plugin(LastLayerPlugin);
+    app.add_plugin(LettersPlugin);
+    app.add_plugin(ReplayPlugin);
     app.add_plugin(RubiksCubePlugin);
+    app.add_plugin(SaveGamePlugin);
+    app.add_plugin(StatisticsPlugin);
+    app.add_plugin(TrainerPlugin);
     app.add_plugin(UiPlugin);
 
     app.add_startup_system(setup);
@@ -56,12 +110,30 @@ fn main() {
     app.run();
 }
 
-fn setup(mut commands: Commands) {
+// `rubiks_cube verify <replay file>` checks a replay without starting the game
+fn verify_replay(path: &str) {
+    match verify::verify_file(std::path::Path::new(path)) {
+        Ok(replay) => {
+            let size = replay.cube_size;
+            println!(
+                "Valid {size}x{size}x{size} solve: {} in {} turns",
+                replay.result,
+                replay.turns.len()
+            );
+        }
+        Err(e) => {
+            eprintln!("Invalid replay: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn setup(mut commands: Commands, game_settings: Res<GameSettings>) {
     // light
     commands.spawn(PointLightBundle {
         point_light: PointLight {
             intensity: 1500.0,
-            shadows_enabled: true,
+            shadows_enabled: game_settings.shadows,
             ..default()
         },
         transform: Transform::from_xyz(2.0, 4.0, 2.0),
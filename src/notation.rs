@@ -0,0 +1,591 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::rubiks_cube::{Axis, LayerTurn, Rotation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    U,
+    D,
+    L,
+    R,
+    F,
+    B,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::U, Face::D, Face::L, Face::R, Face::F, Face::B];
+
+    pub fn axis(self) -> Axis {
+        match self {
+            Face::L | Face::R => Axis::X,
+            Face::D | Face::U => Axis::Y,
+            Face::B | Face::F => Axis::Z,
+        }
+    }
+
+    // whether the face lies on the positive side of its axis
+    pub fn is_positive(self) -> bool {
+        matches!(self, Face::R | Face::U | Face::F)
+    }
+
+    // clockwise turn of the face as seen when looking at the face
+    pub fn clockwise(self) -> Rotation {
+        if self.is_positive() {
+            Rotation::Cw
+        } else {
+            Rotation::Ccw
+        }
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Face::U => 'U',
+            Face::D => 'D',
+            Face::L => 'L',
+            Face::R => 'R',
+            Face::F => 'F',
+            Face::B => 'B',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|face| face.letter() == letter.to_ascii_uppercase())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slice {
+    // between L and R, turns as L
+    M,
+    // between U and D, turns as D
+    E,
+    // between F and B, turns as F
+    S,
+}
+
+impl Slice {
+    fn face(self) -> Face {
+        match self {
+            Slice::M => Face::L,
+            Slice::E => Face::D,
+            Slice::S => Face::F,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    // `depth` outer layers of the face if `wide`, otherwise only the `depth`-th layer
+    Face { face: Face, depth: u32, wide: bool },
+    // all layers except the outer ones
+    Slice(Slice),
+    // whole cube rotation, turns as R, U and F for the X, Y and Z axis
+    Rotation(Axis),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Amount {
+    Single,
+    Double,
+    Prime,
+}
+
+/// Single move in the standard cube notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub kind: MoveKind,
+    pub amount: Amount,
+}
+
+impl Move {
+    pub fn axis(&self) -> Axis {
+        match self.kind {
+            MoveKind::Face { face, .. } => face.axis(),
+            MoveKind::Slice(slice) => slice.face().axis(),
+            MoveKind::Rotation(axis) => axis,
+        }
+    }
+
+    pub fn inverse(self) -> Self {
+        let amount = match self.amount {
+            Amount::Single => Amount::Prime,
+            Amount::Double => Amount::Double,
+            Amount::Prime => Amount::Single,
+        };
+        Self { amount, ..self }
+    }
+
+    /// Turns of the move in the half turn metric, slices count as two
+    /// outer layer turns and rotations are free.
+    pub fn htm(&self) -> usize {
+        match self.kind {
+            MoveKind::Face { .. } => 1,
+            MoveKind::Slice(_) => 2,
+            MoveKind::Rotation(_) => 0,
+        }
+    }
+
+    /// Quarter turns of the cube with `side_size` that make up the move.
+    pub fn layer_turns(&self, side_size: u32) -> Vec<LayerTurn> {
+        let (layer, width, rotation) = match self.kind {
+            MoveKind::Face { face, depth, wide } => {
+                let depth = depth.clamp(1, side_size);
+                let (first, width) = if wide { (depth, depth) } else { (depth, 1) };
+                let layer = if face.is_positive() {
+                    side_size - first
+                } else {
+                    first - width
+                };
+                (layer, width, face.clockwise())
+            }
+            MoveKind::Slice(slice) => (1, side_size.saturating_sub(2), slice.face().clockwise()),
+            MoveKind::Rotation(_) => (0, side_size, Rotation::Cw),
+        };
+        let rotation = match self.amount {
+            Amount::Prime => rotation.inverse(),
+            _ => rotation,
+        };
+        let turn = LayerTurn {
+            axis: self.axis(),
+            layer,
+            width,
+            rotation,
+        };
+        match self.amount {
+            Amount::Double => vec![turn, turn],
+            _ => vec![turn],
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            MoveKind::Face { face, depth, wide } => {
+                if (wide && depth != 2) || (!wide && depth != 1) {
+                    write!(f, "{depth}")?;
+                }
+                write!(f, "{}", face.letter())?;
+                if wide {
+                    write!(f, "w")?;
+                }
+            }
+            MoveKind::Slice(slice) => write!(f, "{slice:?}")?,
+            MoveKind::Rotation(axis) => write!(
+                f,
+                "{}",
+                match axis {
+                    Axis::X => 'x',
+                    Axis::Y => 'y',
+                    Axis::Z => 'z',
+                }
+            )?,
+        }
+        match self.amount {
+            Amount::Single => Ok(()),
+            Amount::Double => write!(f, "2"),
+            Amount::Prime => write!(f, "'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoveError {
+    pub position: usize,
+    pub token: String,
+}
+
+impl Display for ParseMoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid move \"{}\" at {}", self.token, self.position)
+    }
+}
+
+impl std::error::Error for ParseMoveError {}
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_moves(s)?.as_slice() {
+            [m] => Ok(*m),
+            _ => Err(ParseMoveError {
+                position: 0,
+                token: s.to_string(),
+            }),
+        }
+    }
+}
+
+// moves are stored in files in their written form
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parses a sequence of moves like "R U R' U'" or "3Rw2 x M'".
+/// Spaces are optional, brackets are ignored and "//" starts a comment
+/// that lasts until the end of the line.
+pub fn parse_moves(s: &str) -> Result<Vec<Move>, ParseMoveError> {
+    let chars = s.char_indices().collect::<Vec<_>>();
+    let mut moves = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        if c.is_whitespace() || "()[]".contains(c) {
+            i += 1;
+            continue;
+        }
+        if c == '/' && chars.get(i + 1).map(|(_, c)| *c) == Some('/') {
+            while i < chars.len() && chars[i].1 != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        let mut depth = None;
+        while let Some((_, d)) = chars.get(i).filter(|(_, d)| d.is_ascii_digit()) {
+            depth = Some(depth.unwrap_or(0) * 10 + d.to_digit(10).unwrap());
+            i += 1;
+        }
+        let kind = match chars.get(i).map(|(_, c)| *c) {
+            Some(c @ ('U' | 'D' | 'L' | 'R' | 'F' | 'B')) => {
+                i += 1;
+                let wide = chars.get(i).map(|(_, c)| *c) == Some('w');
+                if wide {
+                    i += 1;
+                }
+                Some(MoveKind::Face {
+                    face: Face::from_letter(c).unwrap(),
+                    depth: depth.unwrap_or(if wide { 2 } else { 1 }),
+                    wide,
+                })
+            }
+            // lower case face letters are two layer wide moves
+            Some(c @ ('u' | 'd' | 'l' | 'r' | 'f' | 'b')) if depth.is_none() => {
+                i += 1;
+                Some(MoveKind::Face {
+                    face: Face::from_letter(c).unwrap(),
+                    depth: 2,
+                    wide: true,
+                })
+            }
+            Some(c @ ('M' | 'E' | 'S')) if depth.is_none() => {
+                i += 1;
+                Some(MoveKind::Slice(match c {
+                    'M' => Slice::M,
+                    'E' => Slice::E,
+                    _ => Slice::S,
+                }))
+            }
+            Some(c @ ('x' | 'y' | 'z')) if depth.is_none() => {
+                i += 1;
+                Some(MoveKind::Rotation(match c {
+                    'x' => Axis::X,
+                    'y' => Axis::Y,
+                    _ => Axis::Z,
+                }))
+            }
+            _ => None,
+        };
+        let kind = kind.filter(|kind| !matches!(kind, MoveKind::Face { depth, .. } if *depth == 0));
+        let kind = match kind {
+            Some(kind) => kind,
+            None => {
+                let end = chars
+                    .iter()
+                    .skip(i + 1)
+                    .find(|(_, c)| c.is_whitespace())
+                    .map(|(end, _)| *end)
+                    .unwrap_or(s.len());
+                return Err(ParseMoveError {
+                    position: start,
+                    token: s[start..end].to_string(),
+                });
+            }
+        };
+
+        let mut amount = Amount::Single;
+        if chars.get(i).map(|(_, c)| *c) == Some('2') {
+            amount = Amount::Double;
+            i += 1;
+        }
+        if chars.get(i).map(|(_, c)| *c) == Some('\'') {
+            if amount == Amount::Single {
+                amount = Amount::Prime;
+            }
+            i += 1;
+        }
+        moves.push(Move { kind, amount });
+    }
+    Ok(moves)
+}
+
+pub fn format_moves(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Moves that undo the sequence.
+pub fn invert_moves(moves: &[Move]) -> Vec<Move> {
+    moves.iter().rev().map(|m| m.inverse()).collect()
+}
+
+pub fn moves_layer_turns(moves: &[Move], side_size: u32) -> Vec<LayerTurn> {
+    moves
+        .iter()
+        .flat_map(|m| m.layer_turns(side_size))
+        .collect()
+}
+
+pub fn scramble_length(side_size: u32) -> usize {
+    match side_size {
+        0..=2 => 11,
+        3 => 25,
+        n => 20 * (n as usize - 2),
+    }
+}
+
+pub fn scramble(side_size: u32) -> Vec<Move> {
+    scramble_with_rng(side_size, &fastrand::Rng::new())
+}
+
+/// Random sequence of face turns. Consecutive moves never share an axis
+/// so they can not cancel each other.
+pub fn scramble_with_rng(side_size: u32, rng: &fastrand::Rng) -> Vec<Move> {
+    // 2x2x2 has no centers so turning 3 faces is enough
+    let faces: &[Face] = if side_size <= 2 {
+        &[Face::R, Face::U, Face::F]
+    } else {
+        &Face::ALL
+    };
+    let max_depth = (side_size / 2).max(1);
+
+    let mut moves: Vec<Move> = Vec::new();
+    while moves.len() < scramble_length(side_size) {
+        let face = faces[rng.usize(..faces.len())];
+        if moves.last().map(|m| m.axis()) == Some(face.axis()) {
+            continue;
+        }
+        let depth = rng.u32(1..=max_depth);
+        let amount = [Amount::Single, Amount::Double, Amount::Prime][rng.usize(..3)];
+        moves.push(Move {
+            kind: MoveKind::Face {
+                face,
+                depth,
+                wide: depth > 1,
+            },
+            amount,
+        });
+    }
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rubiks_cube::RubiksCube;
+
+    fn apply(rb: &mut RubiksCube, moves: &str) {
+        for turn in moves_layer_turns(&parse_moves(moves).unwrap(), rb.side_size) {
+            rb.turn(&turn);
+        }
+    }
+
+    #[test]
+    fn move_serde() {
+        let moves = parse_moves("R U2 3Rw' x M").unwrap();
+        let s = ron::to_string(&moves).unwrap();
+        assert!(s.starts_with(r#"["R","U2","#));
+        assert_eq!(ron::from_str::<Vec<Move>>(&s).unwrap(), moves);
+        assert!(ron::from_str::<Move>(r#""Q""#).is_err());
+    }
+
+    #[test]
+    fn notation_parse_and_format() {
+        let moves = parse_moves("R U2 R' 3Rw2 Lw' r M' x y2 z' 2F").unwrap();
+        assert_eq!(format_moves(&moves), "R U2 R' 3Rw2 Lw' Rw M' x y2 z' 2F");
+        assert_eq!(
+            moves[0],
+            Move {
+                kind: MoveKind::Face {
+                    face: Face::R,
+                    depth: 1,
+                    wide: false
+                },
+                amount: Amount::Single
+            }
+        );
+
+        // spaces are optional, brackets and comments are ignored
+        let moves = parse_moves("(RUR'U') [F2'] // comment R\nD").unwrap();
+        assert_eq!(format_moves(&moves), "R U R' U' F2 D");
+
+        assert_eq!(
+            "B'".parse::<Move>(),
+            Ok(Move {
+                kind: MoveKind::Face {
+                    face: Face::B,
+                    depth: 1,
+                    wide: false
+                },
+                amount: Amount::Prime
+            })
+        );
+        assert!("R U".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn notation_parse_errors() {
+        assert_eq!(
+            parse_moves("R U Q2 F"),
+            Err(ParseMoveError {
+                position: 4,
+                token: "Q2".to_string()
+            })
+        );
+        assert!(parse_moves("2x").is_err());
+        assert!(parse_moves("0R").is_err());
+        assert!(parse_moves("3").is_err());
+    }
+
+    #[test]
+    fn notation_layer_turns() {
+        let turns = parse_moves("R").unwrap()[0].layer_turns(3);
+        assert_eq!(
+            turns,
+            vec![LayerTurn {
+                axis: Axis::X,
+                layer: 2,
+                width: 1,
+                rotation: Rotation::Cw
+            }]
+        );
+        let turns = parse_moves("L2").unwrap()[0].layer_turns(3);
+        assert_eq!(
+            turns,
+            vec![
+                LayerTurn {
+                    axis: Axis::X,
+                    layer: 0,
+                    width: 1,
+                    rotation: Rotation::Ccw
+                };
+                2
+            ]
+        );
+        let turns = parse_moves("3Uw'").unwrap()[0].layer_turns(5);
+        assert_eq!(
+            turns,
+            vec![LayerTurn {
+                axis: Axis::Y,
+                layer: 2,
+                width: 3,
+                rotation: Rotation::Ccw
+            }]
+        );
+        let turns = parse_moves("2B").unwrap()[0].layer_turns(4);
+        assert_eq!(
+            turns,
+            vec![LayerTurn {
+                axis: Axis::Z,
+                layer: 1,
+                width: 1,
+                rotation: Rotation::Ccw
+            }]
+        );
+        let turns = parse_moves("M").unwrap()[0].layer_turns(5);
+        assert_eq!(
+            turns,
+            vec![LayerTurn {
+                axis: Axis::X,
+                layer: 1,
+                width: 3,
+                rotation: Rotation::Ccw
+            }]
+        );
+        let turns = parse_moves("y").unwrap()[0].layer_turns(4);
+        assert_eq!(
+            turns,
+            vec![LayerTurn {
+                axis: Axis::Y,
+                layer: 0,
+                width: 4,
+                rotation: Rotation::Cw
+            }]
+        );
+    }
+
+    #[test]
+    fn notation_move_sequences() {
+        // "sexy move" has order 6
+        let mut rb = RubiksCube::new(3);
+        for _ in 0..5 {
+            apply(&mut rb, "R U R' U'");
+            assert!(!rb.is_solved());
+        }
+        apply(&mut rb, "R U R' U'");
+        assert!(rb.is_solved());
+
+        // M = R L' x'
+        let mut slice = RubiksCube::new(3);
+        apply(&mut slice, "M");
+        let mut faces = RubiksCube::new(3);
+        apply(&mut faces, "R L' x'");
+        assert_eq!(slice.cube_to_pos, faces.cube_to_pos);
+
+        // Rw = L x
+        let mut wide = RubiksCube::new(4);
+        apply(&mut wide, "3Rw");
+        let mut faces = RubiksCube::new(4);
+        apply(&mut faces, "L x");
+        assert_eq!(wide.cube_to_pos, faces.cube_to_pos);
+    }
+
+    #[test]
+    fn notation_scramble() {
+        for side_size in 2..=7 {
+            let rng = fastrand::Rng::with_seed(side_size as u64);
+            let moves = scramble_with_rng(side_size, &rng);
+            assert_eq!(moves.len(), scramble_length(side_size));
+            assert!(moves.windows(2).all(|w| w[0].axis() != w[1].axis()));
+            assert!(moves.iter().all(|m| match m.kind {
+                MoveKind::Face { depth, .. } => depth <= (side_size / 2).max(1),
+                _ => false,
+            }));
+
+            let mut rb = RubiksCube::new(side_size);
+            for turn in moves_layer_turns(&moves, side_size) {
+                rb.turn(&turn);
+            }
+            assert!(!rb.is_solved());
+        }
+    }
+
+    #[test]
+    fn notation_inverse_and_htm() {
+        let moves = parse_moves("R U2 M' x 3Rw").unwrap();
+        assert_eq!(format_moves(&invert_moves(&moves)), "3Rw' x' M U2 R'");
+        assert_eq!(moves.iter().map(Move::htm).sum::<usize>(), 5);
+
+        let mut rb = RubiksCube::new(4);
+        apply(&mut rb, "R U2 M' x 3Rw");
+        apply(&mut rb, &format_moves(&invert_moves(&moves)));
+        assert!(rb.is_solved());
+    }
+}
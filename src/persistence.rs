@@ -0,0 +1,80 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_DIR: &str = "rubiks_cube";
+
+/// Directory for config and save files, created on first save.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join(APP_DIR))
+}
+
+/// Reads a RON file from the data dir. Missing or broken files give `None`,
+/// broken ones are reported in the log.
+pub fn load<T: DeserializeOwned>(file_name: &str) -> Option<T> {
+    let path = data_dir()?.join(file_name);
+    let content = fs::read_to_string(&path).ok()?;
+    match ron::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Could not parse {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Writes a value as RON into the data dir, `file_name` can contain
+/// sub directories. Errors are only logged since the game can continue
+/// without the file.
+pub fn save<T: Serialize>(file_name: &str, value: &T) {
+    let Some(path) = data_dir().map(|dir| dir.join(file_name)) else {
+        warn!("No directory to save {file_name} to");
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|content| fs::write(&path, content).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        error!("Could not save {file_name}: {e}");
+    }
+}
+
+/// Deletes a file of the data dir, missing files are fine.
+pub fn remove(file_name: &str) {
+    let Some(path) = data_dir().map(|dir| dir.join(file_name)) else {
+        return;
+    };
+    if let Err(e) = fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            error!("Could not remove {file_name}: {e}");
+        }
+    }
+}
+
+/// Names of the files in a sub directory of the data dir, sorted.
+pub fn files(dir_name: &str) -> Vec<String> {
+    let mut names: Vec<String> = data_dir()
+        .and_then(|dir| fs::read_dir(dir.join(dir_name)).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
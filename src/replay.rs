@@ -0,0 +1,333 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cfop::{self, Split},
+    game_state::{update_solve_timer, GameState, RecordSolve},
+    notation::{moves_layer_turns, Move},
+    persistence,
+    rubiks_cube::{LayerTurn, RubiksCube},
+    rubiks_cube_plugin::{snap_sub_cubes, MoveQueue, Scramble, SubCube, TurnCommitted, TurnSource},
+    solve_timer::{SolveResult, SolveTimer},
+    GameStates,
+};
+
+pub const REPLAYS_DIR: &str = "replays";
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplayViewer::default());
+        app.insert_resource(LastReplay::default());
+        app.insert_resource(ReplayRecording::default());
+
+        app.add_system_set(
+            SystemSet::on_update(GameStates::InGame)
+                .with_system(record_replay.label(RecordSolve).after(update_solve_timer)),
+        );
+        app.add_system_set(SystemSet::on_update(GameStates::Replay).with_system(play_replay));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimedTurn {
+    // time since the start of the inspection
+    pub time: Duration,
+    pub turn: LayerTurn,
+}
+
+/// Solve that can be played back: the scramble applied to a solved cube
+/// and every turn made during the inspection and the solve.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub cube_size: u32,
+    pub scramble: Vec<Move>,
+    pub turns: Vec<TimedTurn>,
+    pub result: SolveResult,
+    // seconds since the unix epoch
+    pub date: u64,
+}
+
+impl Replay {
+    pub fn duration(&self) -> Duration {
+        self.turns.last().map_or(Duration::ZERO, |turn| turn.time)
+    }
+
+    pub fn save(&self) {
+        persistence::save(&format!("{REPLAYS_DIR}/{}.ron", self.date), self);
+    }
+
+    pub fn load(file_name: &str) -> Option<Self> {
+        persistence::load(&format!("{REPLAYS_DIR}/{file_name}"))
+    }
+}
+
+/// Playback state of the opened replay.
+#[derive(Resource, Debug)]
+pub struct ReplayViewer {
+    pub replay: Option<Replay>,
+    // CFOP splits of 3x3x3 replays
+    pub splits: Option<Vec<Split>>,
+    pub speed: f32,
+    pub playing: bool,
+    position: Duration,
+    // number of turns already applied to the cube or queued
+    applied: usize,
+    // the cube has to be rebuilt for the current position
+    seek: bool,
+}
+
+impl Default for ReplayViewer {
+    fn default() -> Self {
+        Self {
+            replay: None,
+            splits: None,
+            speed: 1.0,
+            playing: false,
+            position: Duration::ZERO,
+            applied: 0,
+            seek: true,
+        }
+    }
+}
+
+impl ReplayViewer {
+    pub fn open(&mut self, replay: Replay) {
+        *self = Self {
+            splits: cfop::analyze(&replay),
+            replay: Some(replay),
+            playing: true,
+            ..default()
+        };
+    }
+
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.replay
+            .as_ref()
+            .map_or(Duration::ZERO, Replay::duration)
+    }
+
+    pub fn seek(&mut self, position: Duration) {
+        self.position = position.min(self.duration());
+        self.seek = true;
+    }
+
+    // turns made up to the current position
+    fn turns_done(&self) -> usize {
+        self.replay.as_ref().map_or(0, |replay| {
+            replay
+                .turns
+                .partition_point(|turn| turn.time <= self.position)
+        })
+    }
+
+    /// Turns that rebuild the cube from solved after a seek.
+    fn take_seek(&mut self) -> Option<Vec<LayerTurn>> {
+        if !self.seek {
+            return None;
+        }
+        self.seek = false;
+        self.applied = self.turns_done();
+        let replay = self.replay.as_ref()?;
+        let mut turns = moves_layer_turns(&replay.scramble, replay.cube_size);
+        turns.extend(replay.turns[..self.applied].iter().map(|turn| turn.turn));
+        Some(turns)
+    }
+
+    /// Moves the playback forward and returns the turns reached.
+    fn advance(&mut self, delta: Duration) -> Vec<LayerTurn> {
+        if !self.playing {
+            return Vec::new();
+        }
+        self.position = (self.position + delta.mul_f32(self.speed)).min(self.duration());
+        if self.position >= self.duration() {
+            self.playing = false;
+        }
+        let start = self.applied;
+        self.applied = self.turns_done();
+        self.replay.as_ref().map_or_else(Vec::new, |replay| {
+            replay.turns[start..self.applied]
+                .iter()
+                .map(|turn| turn.turn)
+                .collect()
+        })
+    }
+}
+
+/// Replay of the solve just finished in game, cleared with the next scramble.
+#[derive(Resource, Debug, Default)]
+pub struct LastReplay {
+    pub replay: Option<Replay>,
+    pub splits: Option<Vec<Split>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub elapsed: Duration,
+    pub turns: Vec<TimedTurn>,
+}
+
+/// Turns of the solve in progress, kept as a resource so saved games can
+/// continue the replay.
+#[derive(Resource, Debug, Default)]
+pub struct ReplayRecording(pub Option<Recording>);
+
+fn record_replay(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    scramble: Res<Scramble>,
+    rubiks_cube: Query<&RubiksCube>,
+    mut turn_events: EventReader<TurnCommitted>,
+    mut last_replay: ResMut<LastReplay>,
+    mut recording: ResMut<ReplayRecording>,
+) {
+    match game_state.timer {
+        SolveTimer::Idle => {
+            recording.0 = None;
+            if last_replay.replay.is_some() {
+                *last_replay = LastReplay::default();
+            }
+            turn_events.clear();
+        }
+        SolveTimer::Inspection(_) | SolveTimer::Solving { .. } => {
+            let recording = recording.0.get_or_insert_with(Recording::default);
+            recording.elapsed += time.delta();
+            for TurnCommitted(queued) in turn_events.iter() {
+                if queued.source != TurnSource::Scramble {
+                    recording.turns.push(TimedTurn {
+                        time: recording.elapsed,
+                        turn: queued.turn,
+                    });
+                }
+            }
+        }
+        SolveTimer::Finished(result) => {
+            let (Some(mut recording), Ok(rb)) = (recording.0.take(), rubiks_cube.get_single())
+            else {
+                turn_events.clear();
+                return;
+            };
+            // the solving turn can be committed in the same frame
+            for TurnCommitted(queued) in turn_events.iter() {
+                recording.turns.push(TimedTurn {
+                    time: recording.elapsed,
+                    turn: queued.turn,
+                });
+            }
+            let replay = Replay {
+                cube_size: rb.side_size,
+                scramble: scramble.0.clone(),
+                turns: recording.turns,
+                result,
+                date: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |date| date.as_secs()),
+            };
+            replay.save();
+            *last_replay = LastReplay {
+                splits: cfop::analyze(&replay),
+                replay: Some(replay),
+            };
+        }
+    }
+}
+
+fn play_replay(
+    time: Res<Time>,
+    mut replay_viewer: ResMut<ReplayViewer>,
+    mut move_queue: ResMut<MoveQueue>,
+    mut rubiks_cube: Query<&mut RubiksCube>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+) {
+    // the cube is spawned a frame after entering the state
+    let Ok(mut rb) = rubiks_cube.get_single_mut() else {
+        return;
+    };
+    if let Some(turns) = replay_viewer.take_seek() {
+        move_queue.clear();
+        rb.reset();
+        for turn in turns.iter() {
+            rb.turn(turn);
+        }
+        snap_sub_cubes(&rb, &mut sub_cubes);
+    }
+    for turn in replay_viewer.advance(time.delta()) {
+        move_queue.push(turn, TurnSource::Replay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{notation::parse_moves, rubiks_cube::Axis, solve_timer::Penalty};
+
+    fn replay() -> Replay {
+        let turns = moves_layer_turns(&parse_moves("R U R' U'").unwrap(), 3);
+        Replay {
+            cube_size: 3,
+            scramble: parse_moves("F2 D").unwrap(),
+            turns: turns
+                .into_iter()
+                .enumerate()
+                .map(|(i, turn)| TimedTurn {
+                    time: Duration::from_secs(i as u64 + 1),
+                    turn,
+                })
+                .collect(),
+            result: SolveResult {
+                time: Duration::from_secs(3),
+                penalty: Penalty::None,
+            },
+            date: 0,
+        }
+    }
+
+    #[test]
+    fn replay_playback() {
+        let mut viewer = ReplayViewer::default();
+        viewer.open(replay());
+        assert_eq!(viewer.duration(), Duration::from_secs(4));
+        // the scramble is applied first, F2 is two quarter turns
+        assert_eq!(viewer.take_seek().map(|turns| turns.len()), Some(3));
+        assert_eq!(viewer.take_seek(), None);
+
+        assert!(viewer.advance(Duration::from_millis(500)).is_empty());
+        let turns = viewer.advance(Duration::from_millis(1600));
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].axis, Axis::X);
+        assert_eq!(turns[1].axis, Axis::Y);
+
+        viewer.speed = 4.0;
+        assert_eq!(viewer.advance(Duration::from_secs(1)).len(), 2);
+        assert_eq!(viewer.position(), Duration::from_secs(4));
+        assert!(!viewer.playing);
+    }
+
+    #[test]
+    fn replay_seek() {
+        let mut viewer = ReplayViewer::default();
+        viewer.open(replay());
+        viewer.take_seek();
+        viewer.seek(Duration::from_millis(2500));
+        assert_eq!(viewer.take_seek().map(|turns| turns.len()), Some(5));
+        assert_eq!(viewer.advance(Duration::from_secs(1)).len(), 1);
+
+        // scrubbing back rebuilds the cube
+        viewer.seek(Duration::ZERO);
+        assert_eq!(viewer.take_seek().map(|turns| turns.len()), Some(3));
+    }
+
+    #[test]
+    fn replay_ron() {
+        let replay = replay();
+        let s = ron::to_string(&replay).unwrap();
+        assert_eq!(ron::from_str::<Replay>(&s).unwrap(), replay);
+    }
+}
@@ -1,31 +1,496 @@
 use std::fmt::Display;
 
-use bevy::prelude::{Component, Entity, Vec3};
+use bevy::prelude::{Component, Entity, IVec3, Mat3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Rotation {
     Cw,
     Ccw,
 }
 
-#[derive(Component, Debug, Clone)]
+impl Rotation {
+    pub fn inverse(self) -> Self {
+        match self {
+            Rotation::Cw => Rotation::Ccw,
+            Rotation::Ccw => Rotation::Cw,
+        }
+    }
+
+    // sign of the rotation angle around the positive direction of the axis
+    pub fn angle_sign(self) -> f32 {
+        match self {
+            Rotation::Cw => -1.0,
+            Rotation::Ccw => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn from_vec3(axis: Vec3) -> Option<Self> {
+        if axis == Vec3::X {
+            Some(Axis::X)
+        } else if axis == Vec3::Y {
+            Some(Axis::Y)
+        } else if axis == Vec3::Z {
+            Some(Axis::Z)
+        } else {
+            None
+        }
+    }
+
+    /// Axis with the largest component of the vector.
+    pub fn closest(v: Vec3) -> Self {
+        let v = v.abs();
+        if v.x >= v.y && v.x >= v.z {
+            Axis::X
+        } else if v.y >= v.z {
+            Axis::Y
+        } else {
+            Axis::Z
+        }
+    }
+
+    pub fn to_vec3(self) -> Vec3 {
+        match self {
+            Axis::X => Vec3::X,
+            Axis::Y => Vec3::Y,
+            Axis::Z => Vec3::Z,
+        }
+    }
+
+    pub fn to_ivec3(self) -> IVec3 {
+        match self {
+            Axis::X => IVec3::X,
+            Axis::Y => IVec3::Y,
+            Axis::Z => IVec3::Z,
+        }
+    }
+}
+
+/// Outward normals of the faces of the cube.
+pub const FACE_NORMALS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Rotation of a sub cube relative to its solved orientation.
+/// Stored as images of the base axes, so it never drifts like a `Quat` would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Orientation {
+    pub x: IVec3,
+    pub y: IVec3,
+    pub z: IVec3,
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Orientation {
+    pub const IDENTITY: Self = Self {
+        x: IVec3::X,
+        y: IVec3::Y,
+        z: IVec3::Z,
+    };
+
+    pub fn quarter_turn(axis: Axis, rotation: Rotation) -> Self {
+        let axis = axis.to_ivec3() * rotation.angle_sign() as i32;
+        let rotate = |v: IVec3| axis * axis.dot(v) + axis.cross(v);
+        Self {
+            x: rotate(IVec3::X),
+            y: rotate(IVec3::Y),
+            z: rotate(IVec3::Z),
+        }
+    }
+
+    pub fn apply(&self, v: IVec3) -> IVec3 {
+        self.x * v.x + self.y * v.y + self.z * v.z
+    }
+
+    // orientation that first applies `other` and then `self`
+    pub fn then(&self, other: &Self) -> Self {
+        Self {
+            x: other.apply(self.x),
+            y: other.apply(self.y),
+            z: other.apply(self.z),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            x: IVec3::new(self.x.x, self.y.x, self.z.x),
+            y: IVec3::new(self.x.y, self.y.y, self.z.y),
+            z: IVec3::new(self.x.z, self.y.z, self.z.z),
+        }
+    }
+
+    pub fn to_quat(self) -> Quat {
+        Quat::from_mat3(&Mat3::from_cols(
+            self.x.as_vec3(),
+            self.y.as_vec3(),
+            self.z.as_vec3(),
+        ))
+    }
+}
+
+/// Quarter turn of `width` adjacent layers of the cube starting from the `layer`.
+/// Layers are counted from the negative side of the axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LayerTurn {
+    pub axis: Axis,
+    pub layer: u32,
+    pub width: u32,
+    pub rotation: Rotation,
+}
+
+impl LayerTurn {
+    pub fn inverse(self) -> Self {
+        Self {
+            rotation: self.rotation.inverse(),
+            ..self
+        }
+    }
+
+    pub fn quat(&self, angle: f32) -> Quat {
+        Quat::from_axis_angle(self.axis.to_vec3(), angle)
+    }
+
+    // turn of all layers only changes how the cube is held
+    pub fn is_rotation(&self, side_size: u32) -> bool {
+        self.layer == 0 && self.width >= side_size
+    }
+}
+
+/// Turns made on the cube that can be undone and redone.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MoveHistory {
+    pub done: Vec<LayerTurn>,
+    pub undone: Vec<LayerTurn>,
+}
+
+impl MoveHistory {
+    // any new turn makes the undone turns unreachable
+    pub fn push(&mut self, turn: LayerTurn) {
+        self.done.push(turn);
+        self.undone.clear();
+    }
+
+    // returns the turn that reverts the last done turn
+    pub fn undo(&mut self) -> Option<LayerTurn> {
+        let turn = self.done.pop()?;
+        self.undone.push(turn);
+        Some(turn.inverse())
+    }
+
+    pub fn redo(&mut self) -> Option<LayerTurn> {
+        let turn = self.undone.pop()?;
+        self.done.push(turn);
+        Some(turn)
+    }
+}
+
+/// Positions and orientations of the cubes without their entities, enough
+/// to restore a cube of the same size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CubeArrangement {
+    pub side_size: u32,
+    pub cube_to_pos: Vec<u32>,
+    pub orientations: Vec<Orientation>,
+}
+
+#[derive(Component, Debug, Clone, PartialEq)]
 pub struct RubiksCube {
     pub side_size: u32,
     // maps cube position to the entity
-    pub pos_to_cube: Vec<(Entity, usize)>,
+    // interior cubes are never visible so they don't have an entity
+    pub pos_to_cube: Vec<(Option<Entity>, usize)>,
     // maps entity to cube_position
     pub cube_to_pos: Vec<u32>,
+    // maps cube to its orientation
+    pub orientations: Vec<Orientation>,
+    // pictures make every piece unique, so the centers have to be turned
+    // back and swapped pieces show
+    pub supercube: bool,
 }
 
 impl RubiksCube {
+    pub fn new(side_size: u32) -> Self {
+        Self {
+            side_size,
+            pos_to_cube: (0..side_size.pow(3) as usize).map(|i| (None, i)).collect(),
+            cube_to_pos: (0..side_size.pow(3)).collect(),
+            orientations: vec![Orientation::IDENTITY; side_size.pow(3) as usize],
+            supercube: false,
+        }
+    }
+
+    // puts every cube back to its solved position, entities stay with their cubes
+    pub fn reset(&mut self) {
+        let mut pos_to_cube = self.pos_to_cube.clone();
+        for (entity, cube) in self.pos_to_cube.iter() {
+            pos_to_cube[*cube] = (*entity, *cube);
+        }
+        self.pos_to_cube = pos_to_cube;
+        self.cube_to_pos = (0..self.side_size.pow(3)).collect();
+        self.orientations.fill(Orientation::IDENTITY);
+    }
+
+    pub fn arrangement(&self) -> CubeArrangement {
+        CubeArrangement {
+            side_size: self.side_size,
+            cube_to_pos: self.cube_to_pos.clone(),
+            orientations: self.orientations.clone(),
+        }
+    }
+
+    // moves the cubes to the arranged positions, entities stay with their
+    // cubes; arrangements of other sizes are ignored
+    pub fn restore(&mut self, arrangement: &CubeArrangement) -> bool {
+        let cubes = self.side_size.pow(3) as usize;
+        if arrangement.side_size != self.side_size
+            || arrangement.cube_to_pos.len() != cubes
+            || arrangement.orientations.len() != cubes
+        {
+            return false;
+        }
+        let mut entities = vec![None; cubes];
+        for (entity, cube) in self.pos_to_cube.iter() {
+            entities[*cube] = *entity;
+        }
+        for (cube, pos) in arrangement.cube_to_pos.iter().enumerate() {
+            self.pos_to_cube[*pos as usize] = (entities[cube], cube);
+        }
+        self.cube_to_pos = arrangement.cube_to_pos.clone();
+        self.orientations = arrangement.orientations.clone();
+        true
+    }
+
+    // the cube is solved when every face shows a single color,
+    // so whole cube rotations do not matter
     pub fn is_solved(&self) -> bool {
-        self.pos_to_cube.is_sorted_by_key(|(_, i)| i)
+        let colors_solved = FACE_NORMALS.iter().all(|normal| {
+            let mut stickers = self
+                .face_positions(*normal)
+                .map(|pos| self.sticker(pos, *normal));
+            let first = stickers.next();
+            stickers.all(|sticker| Some(sticker) == first)
+        });
+        colors_solved && (!self.supercube || self.pieces_solved())
+    }
+
+    // every piece is at its home position and orientation up to a rotation
+    // of the whole cube, interior cubes are not seen
+    fn pieces_solved(&self) -> bool {
+        let mut surface = (0..self.side_size.pow(3)).filter(|cube| {
+            let (x, y, z) = self.pos_to_qube_coords(*cube);
+            Self::is_surface_coords(self.side_size, x, y, z)
+        });
+        let Some(first) = surface.next() else {
+            return true;
+        };
+        let rotation = self.orientations[first as usize];
+        std::iter::once(first).chain(surface).all(|cube| {
+            self.orientations[cube as usize] == rotation
+                && self.centered_coords(self.cube_to_pos[cube as usize])
+                    == rotation.apply(self.centered_coords(cube))
+        })
+    }
+
+    // center pieces have a single sticker, every one of the (n - 2)^2 on a face
+    pub fn is_center(&self, pos: u32) -> bool {
+        let (x, y, z) = self.pos_to_qube_coords(pos);
+        let last = self.side_size - 1;
+        self.side_size > 2
+            && [x, y, z]
+                .into_iter()
+                .filter(|c| *c == 0 || *c == last)
+                .count()
+                == 1
+    }
+
+    /// Orientation of the whole cube the center twists are measured against:
+    /// the core moves with the middle centers on odd cubes, even cubes use
+    /// the piece in the left down back corner.
+    pub fn cube_orientation(&self) -> Orientation {
+        let middle = self.side_size / 2;
+        let reference = if self.side_size % 2 == 1 {
+            self.cube_corrds_to_pos(middle, middle, middle)
+        } else {
+            0
+        };
+        self.orientations[self.pos_to_cube[reference as usize].1]
+    }
+
+    /// Clockwise quarter turns of the center piece at the position around
+    /// its face, 0 to 3, relative to `cube_orientation`. Only supercubes
+    /// show it. `None` for other pieces and for centers away from the face
+    /// the cube orientation puts them on.
+    pub fn center_twist(&self, pos: u32) -> Option<u32> {
+        if !self.is_center(pos) {
+            return None;
+        }
+        let (x, y, z) = self.pos_to_qube_coords(pos);
+        let outward = |c: u32| match c {
+            0 => -1,
+            c if c == self.side_size - 1 => 1,
+            _ => 0,
+        };
+        let normal = IVec3::new(outward(x), outward(y), outward(z));
+        let home_normal = self.sticker(pos, normal);
+        // any direction along the home face
+        let along = if home_normal.x == 0 {
+            IVec3::X
+        } else {
+            IVec3::Y
+        };
+        let actual = self.orientations[self.pos_to_cube[pos as usize].1].apply(along);
+        let mut direction = self.cube_orientation().apply(along);
+        for twist in 0..4 {
+            if direction == actual {
+                return Some(twist);
+            }
+            // a clockwise quarter turn seen from outside
+            direction = direction.cross(normal);
+        }
+        None
     }
 
-    pub fn rotate(&mut self, cube_id: usize, cube_normal: Vec3, direction: Vec3) {
-        let (axis, rotation) = Self::select_axis_and_rotation(cube_normal, direction);
+    /// Twists of all center pieces by position, the extra state of a supercube.
+    pub fn center_twists(&self) -> Vec<(u32, Option<u32>)> {
+        (0..self.side_size.pow(3))
+            .filter(|pos| self.is_center(*pos))
+            .map(|pos| (pos, self.center_twist(pos)))
+            .collect()
+    }
+
+    // positions of the cubes on the face with the outward normal
+    pub fn face_positions(&self, normal: IVec3) -> impl Iterator<Item = u32> + '_ {
+        let layer = if normal.max_element() > 0 {
+            self.side_size - 1
+        } else {
+            0
+        };
+        (0..self.side_size.pow(3)).filter(move |pos| {
+            let (x, y, z) = self.pos_to_qube_coords(*pos);
+            let coords = IVec3::new(x as i32, y as i32, z as i32);
+            coords.dot(normal.abs()) == layer as i32
+        })
+    }
+
+    // normal of the face the sticker at the position and with the outward normal
+    // belongs to in the solved cube
+    pub fn sticker(&self, pos: u32, normal: IVec3) -> IVec3 {
+        let cube = self.pos_to_cube[pos as usize].1;
+        self.orientations[cube].inverse().apply(normal)
+    }
+
+    // position of the piece in the direction of the offset with components
+    // in -1..=1 from the center, e.g. (1, 1, 0) is the edge between the
+    // +X and +Y faces; zero components select the middle layer
+    pub fn offset_pos(&self, offset: IVec3) -> u32 {
+        let coord = |c: i32| match c.signum() {
+            -1 => 0,
+            0 => self.side_size / 2,
+            _ => self.side_size - 1,
+        };
+        self.cube_corrds_to_pos(coord(offset.x), coord(offset.y), coord(offset.z))
+    }
+
+    // color of the face center, the color of a face is the home face of its sticker
+    pub fn center_sticker(&self, normal: IVec3) -> IVec3 {
+        self.sticker(self.offset_pos(normal), normal)
+    }
 
-        let selection = self.select_rotation(cube_id, axis);
+    // stickers of the piece match the centers of their faces, so pieces
+    // are solved relative to the centers whatever the cube orientation
+    pub fn piece_solved(&self, offset: IVec3) -> bool {
+        let pos = self.offset_pos(offset);
+        [IVec3::X, IVec3::Y, IVec3::Z]
+            .iter()
+            .map(|axis| *axis * offset)
+            .filter(|normal| *normal != IVec3::ZERO)
+            .all(|normal| self.sticker(pos, normal) == self.center_sticker(normal))
+    }
+
+    // every sticker of the face has the color of its center
+    pub fn face_oriented(&self, normal: IVec3) -> bool {
+        let center = self.center_sticker(normal);
+        self.face_positions(normal)
+            .all(|pos| self.sticker(pos, normal) == center)
+    }
+
+    // number of pieces with a sticker that differs from the color of its face,
+    // the color of a face is the one most of its stickers show
+    pub fn pieces_off(&self) -> usize {
+        let face_colors = FACE_NORMALS.map(|normal| {
+            let stickers: Vec<IVec3> = self
+                .face_positions(normal)
+                .map(|pos| self.sticker(pos, normal))
+                .collect();
+            FACE_NORMALS
+                .into_iter()
+                .max_by_key(|color| stickers.iter().filter(|s| *s == color).count())
+                .unwrap()
+        });
+        let outer = self.side_size as i32 - 1;
+        (0..self.side_size.pow(3))
+            .filter(|pos| {
+                let coords = self.centered_coords(*pos);
+                FACE_NORMALS.iter().zip(face_colors).any(|(normal, color)| {
+                    coords.dot(*normal) == outer && self.sticker(*pos, *normal) != color
+                })
+            })
+            .count()
+    }
+
+    // coordinates of the cube center with the center of the rubiks cube
+    // at the origin and with a distance of 2 between the neighbour cubes
+    pub fn centered_coords(&self, pos: u32) -> IVec3 {
+        let (x, y, z) = self.pos_to_qube_coords(pos);
+        IVec3::new(x as i32, y as i32, z as i32) * 2 - IVec3::splat(self.side_size as i32 - 1)
+    }
+
+    // turn of the layer containing the cube around the rotation_axis
+    pub fn layer_turn(&self, cube_id: usize, rotation_axis: Vec3, rotation: Rotation) -> LayerTurn {
+        let (x, y, z) = self.pos_to_qube_coords(self.cube_to_pos[cube_id]);
+        let axis = Axis::from_vec3(rotation_axis).unwrap_or_else(|| {
+            unreachable!("Axis of rotation should only be a unit base vector: {rotation_axis}")
+        });
+        let layer = match axis {
+            Axis::X => x,
+            Axis::Y => y,
+            Axis::Z => z,
+        };
+        LayerTurn {
+            axis,
+            layer,
+            width: 1,
+            rotation,
+        }
+    }
+
+    pub fn turn(&mut self, turn: &LayerTurn) {
+        for layer in turn.layer..turn.layer + turn.width {
+            self.turn_layer(turn.axis, layer, turn.rotation);
+        }
+    }
+
+    fn turn_layer(&mut self, axis: Axis, layer: u32, rotation: Rotation) {
+        let selection = self.select_layer(axis, layer);
         let rotated = self.rotate_indices(&selection, rotation);
 
         let mut pos_to_cube_new = self.pos_to_cube.clone();
@@ -33,8 +498,11 @@ impl RubiksCube {
         for (s, r) in selection.iter().zip(rotated.iter()) {
             pos_to_cube_new[*s as usize] = self.pos_to_cube[*r as usize];
         }
+        let quarter_turn = Orientation::quarter_turn(axis, rotation);
         for s in selection {
-            cube_to_pos_new[pos_to_cube_new[s as usize].1] = s;
+            let cube = pos_to_cube_new[s as usize].1;
+            cube_to_pos_new[cube] = s;
+            self.orientations[cube] = self.orientations[cube].then(&quarter_turn);
         }
         self.pos_to_cube = pos_to_cube_new;
         self.cube_to_pos = cube_to_pos_new;
@@ -61,10 +529,25 @@ impl RubiksCube {
     pub fn select_rotation_entities(&self, cube_id: usize, rotation_axis: Vec3) -> Vec<Entity> {
         self.select_rotation(cube_id, rotation_axis)
             .into_iter()
-            .map(|i| self.pos_to_cube[i as usize].0)
+            .filter_map(|i| self.pos_to_cube[i as usize].0)
             .collect()
     }
 
+    pub fn select_turn_entities(&self, turn: &LayerTurn) -> Vec<Entity> {
+        (turn.layer..turn.layer + turn.width)
+            .flat_map(|layer| self.select_layer(turn.axis, layer))
+            .filter_map(|i| self.pos_to_cube[i as usize].0)
+            .collect()
+    }
+
+    pub fn select_layer(&self, axis: Axis, layer: u32) -> Vec<u32> {
+        match axis {
+            Axis::X => self.select_x_layer(layer),
+            Axis::Y => self.select_y_layer(layer),
+            Axis::Z => self.select_z_layer(layer),
+        }
+    }
+
     pub fn select_rotation(&self, cube_id: usize, rotation_axis: Vec3) -> Vec<u32> {
         let cube_pos = self.cube_to_pos[cube_id];
         let (x, y, z) = self.pos_to_qube_coords(cube_pos);
@@ -92,6 +575,10 @@ impl RubiksCube {
         x * side_size * side_size + y * side_size + z
     }
 
+    pub fn is_surface_coords(side_size: u32, x: u32, y: u32, z: u32) -> bool {
+        [x, y, z].iter().any(|c| *c == 0 || *c == side_size - 1)
+    }
+
     pub fn cube_corrds_to_pos(&self, x: u32, y: u32, z: u32) -> u32 {
         Self::corrds_to_pos(self.side_size, x, y, z)
     }
@@ -167,7 +654,7 @@ mod tests {
                 for z in 0..sides {
                     let index = RubiksCube::corrds_to_pos(sides, x, y, z);
                     let entity = Entity::from_raw(index);
-                    sub_cubes.push((entity, index as usize));
+                    sub_cubes.push((Some(entity), index as usize));
                 }
             }
         }
@@ -175,9 +662,17 @@ mod tests {
             side_size: sides,
             pos_to_cube: sub_cubes,
             cube_to_pos: (0..sides.pow(3)).collect(),
+            orientations: vec![Orientation::IDENTITY; sides.pow(3) as usize],
+            supercube: false,
         }
     }
 
+    fn rotate(rb: &mut RubiksCube, cube_id: usize, cube_normal: Vec3, direction: Vec3) {
+        let (axis, rotation) = RubiksCube::select_axis_and_rotation(cube_normal, direction);
+        let turn = rb.layer_turn(cube_id, axis, rotation);
+        rb.turn(&turn);
+    }
+
     #[test]
     fn rb_select_axis_and_rotation() {
         // X
@@ -286,35 +781,35 @@ mod tests {
     #[test]
     fn rb_rotate_single() {
         let mut rb = generate_rb(3);
-        rb.rotate(0, Vec3::NEG_Y, Vec3::Z);
+        rotate(&mut rb, 0, Vec3::NEG_Y, Vec3::Z);
         let expected_cubes_pos = vec![
             6, 3, 0, 7, 4, 1, 8, 5, 2, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
             24, 25, 26,
         ]
         .into_iter()
-        .map(|c| (Entity::from_raw(c), c as usize))
+        .map(|c| (Some(Entity::from_raw(c)), c as usize))
         .collect::<Vec<_>>();
         assert_eq!(rb.pos_to_cube, expected_cubes_pos);
 
         let mut rb = generate_rb(3);
-        rb.rotate(0, Vec3::NEG_X, Vec3::Z);
+        rotate(&mut rb, 0, Vec3::NEG_X, Vec3::Z);
         let expected_cubes_pos = vec![
             18, 9, 0, 3, 4, 5, 6, 7, 8, 19, 10, 1, 12, 13, 14, 15, 16, 17, 20, 11, 2, 21, 22, 23,
             24, 25, 26,
         ]
         .into_iter()
-        .map(|c| (Entity::from_raw(c), c as usize))
+        .map(|c| (Some(Entity::from_raw(c)), c as usize))
         .collect::<Vec<_>>();
         assert_eq!(rb.pos_to_cube, expected_cubes_pos);
 
         let mut rb = generate_rb(3);
-        rb.rotate(0, Vec3::NEG_X, Vec3::Y);
+        rotate(&mut rb, 0, Vec3::NEG_X, Vec3::Y);
         let expected_cubes_pos = vec![
             18, 1, 2, 9, 4, 5, 0, 7, 8, 21, 10, 11, 12, 13, 14, 3, 16, 17, 24, 19, 20, 15, 22, 23,
             6, 25, 26,
         ]
         .into_iter()
-        .map(|c| (Entity::from_raw(c), c as usize))
+        .map(|c| (Some(Entity::from_raw(c)), c as usize))
         .collect::<Vec<_>>();
         assert_eq!(rb.pos_to_cube, expected_cubes_pos);
     }
@@ -322,33 +817,33 @@ mod tests {
     #[test]
     fn rb_rotate_multiple() {
         let mut rb = generate_rb(3);
-        rb.rotate(0, Vec3::NEG_Y, Vec3::Z);
+        rotate(&mut rb, 0, Vec3::NEG_Y, Vec3::Z);
         let expected_cubes_pos = vec![
             6, 3, 0, 7, 4, 1, 8, 5, 2, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
             24, 25, 26,
         ]
         .into_iter()
-        .map(|c| (Entity::from_raw(c), c as usize))
+        .map(|c| (Some(Entity::from_raw(c)), c as usize))
         .collect::<Vec<_>>();
         assert_eq!(rb.pos_to_cube, expected_cubes_pos);
 
-        rb.rotate(6, Vec3::NEG_X, Vec3::Z);
+        rotate(&mut rb, 6, Vec3::NEG_X, Vec3::Z);
         let expected_cubes_pos = vec![
             18, 9, 6, 7, 4, 1, 8, 5, 2, 19, 10, 3, 12, 13, 14, 15, 16, 17, 20, 11, 0, 21, 22, 23,
             24, 25, 26,
         ]
         .into_iter()
-        .map(|c| (Entity::from_raw(c), c as usize))
+        .map(|c| (Some(Entity::from_raw(c)), c as usize))
         .collect::<Vec<_>>();
         assert_eq!(rb.pos_to_cube, expected_cubes_pos);
 
-        rb.rotate(20, Vec3::NEG_Y, Vec3::Z);
+        rotate(&mut rb, 20, Vec3::NEG_Y, Vec3::Z);
         let expected_cubes_pos = vec![
             18, 9, 6, 7, 4, 1, 8, 5, 2, 19, 10, 3, 12, 13, 14, 15, 16, 17, 24, 21, 20, 25, 22, 11,
             26, 23, 0,
         ]
         .into_iter()
-        .map(|c| (Entity::from_raw(c), c as usize))
+        .map(|c| (Some(Entity::from_raw(c)), c as usize))
         .collect::<Vec<_>>();
         assert_eq!(rb.pos_to_cube, expected_cubes_pos);
     }
@@ -426,6 +921,376 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rb_turn_and_inverse() {
+        let mut rb = generate_rb(3);
+        let turn = rb.layer_turn(0, Vec3::X, Rotation::Cw);
+        assert_eq!(
+            turn,
+            LayerTurn {
+                axis: Axis::X,
+                layer: 0,
+                width: 1,
+                rotation: Rotation::Cw
+            }
+        );
+        rb.turn(&turn);
+        assert!(!rb.is_solved());
+        rb.turn(&turn.inverse());
+        assert!(rb.is_solved());
+        assert_eq!(rb.pos_to_cube, generate_rb(3).pos_to_cube);
+    }
+
+    #[test]
+    fn rb_move_history() {
+        let turn_1 = LayerTurn {
+            axis: Axis::X,
+            layer: 0,
+            width: 1,
+            rotation: Rotation::Cw,
+        };
+        let turn_2 = LayerTurn {
+            axis: Axis::Y,
+            layer: 2,
+            width: 1,
+            rotation: Rotation::Ccw,
+        };
+        let mut history = MoveHistory::default();
+        assert_eq!(history.undo(), None);
+        history.push(turn_1);
+        history.push(turn_2);
+        assert_eq!(history.undo(), Some(turn_2.inverse()));
+        assert_eq!(history.redo(), Some(turn_2));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(turn_2.inverse()));
+        assert_eq!(history.undo(), Some(turn_1.inverse()));
+        assert_eq!(history.undo(), None);
+        history.redo();
+        // new turn drops the undone ones
+        history.push(turn_2);
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.done, vec![turn_1, turn_2]);
+    }
+
+    #[test]
+    fn rb_orientation() {
+        let turn = Orientation::quarter_turn(Axis::X, Rotation::Ccw);
+        assert_eq!(turn.apply(IVec3::Y), IVec3::Z);
+        assert_eq!(turn.then(&turn.inverse()), Orientation::IDENTITY);
+        let quat = turn.to_quat();
+        assert!((quat.mul_vec3(Vec3::Y) - Vec3::Z).length() < 1e-6);
+        // first around X and then around Y
+        let composed = turn.then(&Orientation::quarter_turn(Axis::Y, Rotation::Cw));
+        assert_eq!(composed.apply(IVec3::Y), IVec3::NEG_X);
+
+        let mut full = Orientation::IDENTITY;
+        for _ in 0..4 {
+            full = full.then(&Orientation::quarter_turn(Axis::Z, Rotation::Cw));
+        }
+        assert_eq!(full, Orientation::IDENTITY);
+    }
+
+    #[test]
+    fn rb_orientation_follows_position() {
+        // every cube is moved as a rigid body, so its position is
+        // always its solved position rotated by its orientation
+        let mut rb = RubiksCube::new(4);
+        let turns = [
+            (Axis::X, 0, Rotation::Cw),
+            (Axis::Y, 3, Rotation::Ccw),
+            (Axis::Z, 1, Rotation::Cw),
+            (Axis::X, 2, Rotation::Ccw),
+            (Axis::Y, 1, Rotation::Cw),
+            (Axis::Z, 3, Rotation::Cw),
+        ];
+        for (axis, layer, rotation) in turns {
+            rb.turn(&LayerTurn {
+                axis,
+                layer,
+                width: 1,
+                rotation,
+            });
+            for cube in 0..rb.cube_to_pos.len() {
+                let home = rb.centered_coords(cube as u32);
+                let current = rb.centered_coords(rb.cube_to_pos[cube]);
+                assert_eq!(rb.orientations[cube].apply(home), current);
+            }
+        }
+    }
+
+    #[test]
+    fn rb_orientation_inverse() {
+        let turns = [Axis::X, Axis::Y, Axis::Z]
+            .into_iter()
+            .flat_map(|axis| {
+                [Rotation::Cw, Rotation::Ccw]
+                    .map(|rotation| Orientation::quarter_turn(axis, rotation))
+            })
+            .collect::<Vec<_>>();
+        for first in &turns {
+            for second in &turns {
+                let composed = first.then(second);
+                for v in [IVec3::X, IVec3::Y, IVec3::Z, IVec3::new(1, -2, 3)] {
+                    assert_eq!(composed.apply(v), second.apply(first.apply(v)));
+                    assert_eq!(composed.inverse().apply(composed.apply(v)), v);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rb_stickers() {
+        let mut rb = RubiksCube::new(3);
+        for normal in FACE_NORMALS {
+            assert_eq!(rb.face_positions(normal).count(), 9);
+            assert!(rb
+                .face_positions(normal)
+                .all(|pos| rb.sticker(pos, normal) == normal));
+        }
+
+        let turn = LayerTurn {
+            axis: Axis::X,
+            layer: 0,
+            width: 1,
+            rotation: Rotation::Cw,
+        };
+        rb.turn(&turn);
+        assert!(!rb.is_solved());
+        // the turned layer brings the stickers of another face to the top,
+        // the rest of the top face and the centers stay
+        for pos in rb.face_positions(IVec3::Y) {
+            let (x, _, _) = rb.pos_to_qube_coords(pos);
+            assert_eq!(rb.sticker(pos, IVec3::Y) == IVec3::Y, x != 0);
+        }
+        for normal in FACE_NORMALS {
+            assert_eq!(rb.center_sticker(normal), normal);
+        }
+        // the left face only turned in place
+        assert!(rb.face_oriented(IVec3::NEG_X));
+
+        for _ in 0..3 {
+            rb.turn(&turn);
+        }
+        assert!(rb.is_solved());
+        assert_eq!(rb.orientations, RubiksCube::new(3).orientations);
+    }
+
+    #[test]
+    fn rb_is_solved_after_cube_rotation() {
+        let mut rb = RubiksCube::new(3);
+        let whole_cube = LayerTurn {
+            axis: Axis::Y,
+            layer: 0,
+            width: 3,
+            rotation: Rotation::Cw,
+        };
+        rb.turn(&whole_cube);
+        assert!(rb.is_solved());
+        assert_ne!(rb.cube_to_pos, RubiksCube::new(3).cube_to_pos);
+
+        rb.turn(&LayerTurn {
+            width: 1,
+            ..whole_cube
+        });
+        assert!(!rb.is_solved());
+    }
+
+    #[test]
+    fn rb_supercube_is_solved() {
+        let mut rb = RubiksCube::new(3);
+        rb.supercube = true;
+        let whole_cube = LayerTurn {
+            axis: Axis::X,
+            layer: 0,
+            width: 3,
+            rotation: Rotation::Ccw,
+        };
+        rb.turn(&whole_cube);
+        assert!(rb.is_solved());
+
+        // the up center turned in place shows only on a supercube
+        let center = rb.pos_to_cube[rb.offset_pos(IVec3::Y) as usize].1;
+        rb.orientations[center] = rb.orientations[center]
+            .then(&Orientation::quarter_turn(Axis::Y, Rotation::Cw))
+            .then(&Orientation::quarter_turn(Axis::Y, Rotation::Cw));
+        assert!(!rb.is_solved());
+        rb.supercube = false;
+        assert!(rb.is_solved());
+    }
+
+    #[test]
+    fn rb_center_twists() {
+        let turned = |size: u32, moves: &str| {
+            let mut rb = RubiksCube::new(size);
+            let moves = crate::notation::parse_moves(moves).unwrap();
+            for turn in crate::notation::moves_layer_turns(&moves, size) {
+                rb.turn(&turn);
+            }
+            rb
+        };
+        let twist = |rb: &RubiksCube, normal: IVec3| rb.center_twist(rb.offset_pos(normal));
+
+        let rb = RubiksCube::new(3);
+        assert_eq!(rb.center_twists().len(), 6);
+        assert!(rb
+            .center_twists()
+            .iter()
+            .all(|(_, twist)| *twist == Some(0)));
+        assert_eq!(rb.center_twist(rb.offset_pos(IVec3::ONE)), None);
+
+        for (moves, expected) in [("U", 1), ("U2", 2), ("U'", 3), ("R", 0)] {
+            assert_eq!(
+                twist(&turned(3, moves), IVec3::Y),
+                Some(expected),
+                "{moves}"
+            );
+        }
+        // the up center goes to the back and then to the right
+        assert_eq!(twist(&turned(3, "U x y"), IVec3::X), Some(1));
+        // the right center after x is the same piece as before
+        assert_eq!(twist(&turned(3, "R x"), IVec3::X), Some(1));
+        // the front center turns with the whole cube and the middle slices
+        assert_eq!(twist(&turned(3, "x M'"), IVec3::Z), Some(0));
+
+        // the inner centers of even cubes against the left down back corner
+        let rb = turned(4, "U");
+        assert_eq!(rb.center_twists().len(), 24);
+        let up_centers: Vec<_> = rb
+            .face_positions(IVec3::Y)
+            .filter_map(|pos| rb.center_twist(pos))
+            .collect();
+        assert_eq!(up_centers, vec![1; 4]);
+        assert!(rb.is_center(rb.cube_corrds_to_pos(1, 3, 2)));
+        assert!(!RubiksCube::new(2).is_center(0));
+    }
+
+    #[test]
+    fn rb_new_matches_generated() {
+        let rb = RubiksCube::new(4);
+        let generated = generate_rb(4);
+        assert_eq!(rb.cube_to_pos, generated.cube_to_pos);
+        assert!(rb.pos_to_cube.iter().all(|(entity, _)| entity.is_none()));
+        assert!(rb.is_solved());
+    }
+
+    #[test]
+    fn rb_reset() {
+        let mut rb = RubiksCube::new(3);
+        for (cube, (entity, _)) in rb.pos_to_cube.iter_mut().enumerate() {
+            *entity = Some(Entity::from_raw(cube as u32));
+        }
+        let solved = rb.clone();
+        for turn in [
+            LayerTurn {
+                axis: Axis::X,
+                layer: 2,
+                width: 1,
+                rotation: Rotation::Cw,
+            },
+            LayerTurn {
+                axis: Axis::Y,
+                layer: 0,
+                width: 2,
+                rotation: Rotation::Ccw,
+            },
+        ] {
+            rb.turn(&turn);
+        }
+        assert!(!rb.is_solved());
+        rb.reset();
+        assert_eq!(rb, solved);
+    }
+
+    #[test]
+    fn rb_piece_queries() {
+        let mut rb = RubiksCube::new(3);
+        assert_eq!(rb.offset_pos(IVec3::new(1, 1, 0)), 2 * 9 + 2 * 3 + 1);
+        assert!(FACE_NORMALS.iter().all(|n| rb.center_sticker(*n) == *n));
+
+        // R moves the pieces of the +X layer
+        rb.turn(&LayerTurn {
+            axis: Axis::X,
+            layer: 2,
+            width: 1,
+            rotation: Rotation::Cw,
+        });
+        assert!(!rb.piece_solved(IVec3::new(1, 1, 0)));
+        assert!(!rb.piece_solved(IVec3::new(1, 1, 1)));
+        assert!(rb.piece_solved(IVec3::new(-1, 1, 0)));
+        assert!(rb.piece_solved(IVec3::X));
+        assert!(rb.face_oriented(IVec3::X));
+        assert!(!rb.face_oriented(IVec3::Y));
+
+        // M moves the centers, the pieces are solved relative to them
+        let mut rb = RubiksCube::new(3);
+        rb.turn(&LayerTurn {
+            axis: Axis::X,
+            layer: 1,
+            width: 1,
+            rotation: Rotation::Ccw,
+        });
+        assert_eq!(rb.center_sticker(IVec3::Y), IVec3::NEG_Z);
+        assert!(!rb.piece_solved(IVec3::new(1, 1, 0)));
+        assert!(rb.piece_solved(IVec3::new(0, 1, 1)));
+    }
+
+    #[test]
+    fn rb_pieces_off() {
+        let r = LayerTurn {
+            axis: Axis::X,
+            layer: 2,
+            width: 1,
+            rotation: Rotation::Cw,
+        };
+        let mut rb = RubiksCube::new(3);
+        assert_eq!(rb.pieces_off(), 0);
+        rb.turn(&r);
+        assert_eq!(rb.pieces_off(), 8);
+
+        // a whole cube rotation keeps every piece on its face
+        let mut rb = RubiksCube::new(4);
+        rb.turn(&LayerTurn {
+            layer: 0,
+            width: 4,
+            ..r
+        });
+        assert_eq!(rb.pieces_off(), 0);
+        rb.turn(&LayerTurn { layer: 3, ..r });
+        assert_eq!(rb.pieces_off(), 12);
+    }
+
+    #[test]
+    fn rb_arrangement() {
+        let mut rb = generate_rb(3);
+        rotate(&mut rb, 0, Vec3::Y, Vec3::X);
+        rotate(&mut rb, 2, Vec3::Z, Vec3::Y);
+        let arrangement = rb.arrangement();
+
+        let mut restored = generate_rb(3);
+        assert!(restored.restore(&arrangement));
+        assert_eq!(restored, rb);
+        assert!(!generate_rb(4).restore(&arrangement));
+    }
+
+    #[test]
+    fn axis_closest() {
+        assert_eq!(Axis::closest(Vec3::new(0.9, -0.1, 0.3)), Axis::X);
+        assert_eq!(Axis::closest(Vec3::new(0.2, -0.7, 0.5)), Axis::Y);
+        assert_eq!(Axis::closest(Vec3::new(0.2, -0.1, -0.5)), Axis::Z);
+    }
+
+    #[test]
+    fn rb_surface_coords() {
+        let sides = 4;
+        let surface = (0..sides)
+            .flat_map(|x| (0..sides).flat_map(move |y| (0..sides).map(move |z| (x, y, z))))
+            .filter(|(x, y, z)| RubiksCube::is_surface_coords(sides, *x, *y, *z))
+            .count();
+        // all cubes except the 2x2x2 core
+        assert_eq!(surface, 64 - 8);
+        assert!(RubiksCube::is_surface_coords(2, 1, 1, 1));
+    }
+
     #[test]
     fn rb_select_rotation() {
         let rb = generate_rb(3);
@@ -1,32 +1,69 @@
-use bevy::{prelude::*, render::primitives::Aabb};
+use std::collections::VecDeque;
+
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{primitives::Aabb, view::NoFrustumCulling},
+};
+use bevy_egui::EguiContext;
 use bevy_kira_audio::{Audio, AudioControl};
 
 use crate::{
     audio::GameSounds,
+    color_scheme::ColorScheme,
+    cube_instancing::{InstancedSubCubes, SubCubeInstance},
     cube_material::CubeMaterial,
     cursor::{CollinearAxisProjection, CursorCollinearAxis, CursorRay},
-    ray_extension::RayExtension,
-    rubiks_cube::{Rotation, RubiksCube},
     game_settings::GameSettings,
+    input_bindings::{Action, ActionInput},
+    notation::{self, moves_layer_turns, Amount, Move, MoveKind},
+    ray_extension::RayExtension,
+    replay::ReplayViewer,
+    rubiks_cube::{Axis, LayerTurn, MoveHistory, Rotation, RubiksCube},
+    save_game::SaveGame,
     GameStates,
 };
 
-const CUBE_SIDES: u32 = 3;
-const CUBE_SIDE_SIZE: f32 = 0.1;
-const CUBE_SPACING: f32 = 0.105;
+pub const MIN_CUBE_SIDES: u32 = 2;
+pub const MAX_CUBE_SIDES: u32 = 17;
+pub const CUBE_SIDE_SIZE: f32 = 0.1;
+pub const CUBE_SPACING: f32 = 0.105;
+// cubes of any size are scaled to take the same space as the 3x3x3 one
+const REFERENCE_CUBE_SIDES: f32 = 3.0;
+// cursor distance in pixels before a whole cube drag picks its direction
+const WHOLE_CUBE_DRAG_THRESHOLD: f32 = 10.0;
+// stickers of blindfolded solves
+const HIDDEN_STICKER_COLOR: Color = Color::GRAY;
 
 pub struct RubiksCubePlugin;
 
 impl Plugin for RubiksCubePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameStates::InGame).with_system(init_rb));
+        app.add_event::<RubiksCubeEvent>();
+        app.add_event::<TurnCommitted>();
+
+        // the trainer and the fewest moves sandbox turn the same cube as the game
+        for state in [GameStates::InGame, GameStates::Trainer, GameStates::Fmc] {
+            app.add_system_set(SystemSet::on_enter(state).with_system(init_rb));
+            app.add_system_set(
+                SystemSet::on_update(state)
+                    .with_system(selecting_sub_cube)
+                    .with_system(rotate_side.after(selecting_sub_cube))
+                    .with_system(stop_rotation.after(rotate_side))
+                    .with_system(rotate_whole_cube.after(selecting_sub_cube))
+                    .with_system(stop_whole_cube_rotation.after(rotate_whole_cube))
+                    .with_system(rubiks_cube_events.after(stop_rotation))
+                    .with_system(animate_move_queue.after(rubiks_cube_events)),
+            );
+            app.add_system_set(SystemSet::on_exit(state).with_system(clean_rb));
+        }
+
+        // replays are played back on the same cube without the player input
+        app.add_system_set(SystemSet::on_enter(GameStates::Replay).with_system(init_rb));
         app.add_system_set(
-            SystemSet::on_update(GameStates::InGame)
-                .with_system(selecting_sub_cube)
-                .with_system(rotate_side.after(selecting_sub_cube))
-                .with_system(stop_rotation.after(rotate_side)),
+            SystemSet::on_update(GameStates::Replay).with_system(animate_move_queue),
         );
-        app.add_system_set(SystemSet::on_exit(GameStates::InGame).with_system(clean_rb));
+        app.add_system_set(SystemSet::on_exit(GameStates::Replay).with_system(clean_rb));
     }
 }
 
@@ -45,133 +82,349 @@ pub struct CurrentlySelectedSubCubeRayNormal(pub Option<Vec3>);
 #[derive(Resource, Debug, Default, Clone, Copy)]
 struct RotationAngle(f32);
 
+// cursor drag that started off the cube and rotates the whole cube
+#[derive(Resource, Debug, Default, Clone, Copy)]
+struct WholeCubeDrag(Option<CubeDrag>);
+
+#[derive(Debug, Clone, Copy)]
+struct CubeDrag {
+    start: Vec2,
+    // picked once the cursor moved far enough: screen direction of
+    // the drag and the axis it rotates around with a positive angle
+    direction: Option<(Vec2, Vec3)>,
+    angle: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RubiksCubeEvent {
+    Undo,
+    Redo,
+    Scramble,
+    // finish all queued turns immediately
+    SkipTurns,
+    // turn made by the player without dragging, e.g. from the keyboard
+    Turn(Move),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnSource {
+    Player,
+    Undo,
+    Redo,
+    Scramble,
+    Replay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueuedTurn {
+    pub turn: LayerTurn,
+    pub source: TurnSource,
+}
+
+/// Sent every time a turn is committed to the `RubiksCube`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnCommitted(pub QueuedTurn);
+
+#[derive(Debug, Clone)]
+struct AnimatedTurn {
+    queued: QueuedTurn,
+    entities: Vec<Entity>,
+    duration: f32,
+    elapsed: f32,
+    angle: f32,
+}
+
+/// Turns that are played without the mouse, e.g. undo, redo or scramble.
+/// Turns are animated one by one in the order they were pushed.
+#[derive(Resource, Debug, Default)]
+pub struct MoveQueue {
+    turns: VecDeque<QueuedTurn>,
+    current: Option<AnimatedTurn>,
+    // number of turns pushed since the queue was empty
+    total: usize,
+    skip: bool,
+}
+
+impl MoveQueue {
+    pub fn push(&mut self, turn: LayerTurn, source: TurnSource) {
+        self.turns.push_back(QueuedTurn { turn, source });
+        self.total += 1;
+    }
+
+    pub fn extend(&mut self, turns: impl IntoIterator<Item = LayerTurn>, source: TurnSource) {
+        for turn in turns {
+            self.push(turn, source);
+        }
+    }
+
+    // number of turns that are not committed yet
+    pub fn len(&self) -> usize {
+        self.turns.len() + self.current.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // number of committed turns out of all turns pushed since the queue was empty
+    pub fn progress(&self) -> (usize, usize) {
+        (self.total - self.len(), self.total)
+    }
+
+    pub fn is_scrambling(&self) -> bool {
+        self.current
+            .iter()
+            .map(|animated| &animated.queued)
+            .chain(self.turns.iter())
+            .any(|queued| queued.source == TurnSource::Scramble)
+    }
+
+    pub fn skip_to_end(&mut self) {
+        self.skip = true;
+    }
+
+    // drops the queued turns, the animated one is left half way so the
+    // sub cubes have to be snapped to the `RubiksCube` afterwards
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Moves of the last scramble applied to the cube.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Scramble(pub Vec<Move>);
+
 #[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
-struct SubCube(usize);
+pub struct SubCube(usize);
+
+// All the sub cubes share one material, the shader leaves the inner faces
+// of each sub cube black.
+#[derive(Resource, Debug, Default)]
+pub struct SubCubeMaterial {
+    handle: Handle<CubeMaterial>,
+    // supercubes mark the rotation of their centers
+    center_markers: bool,
+    cube_sides: u32,
+}
+
+impl SubCubeMaterial {
+    /// Paints every sticker gray or in the color of its face.
+    pub fn paint(
+        &self,
+        hidden: bool,
+        color_scheme: &ColorScheme,
+        materials: &mut Assets<CubeMaterial>,
+    ) {
+        if let Some(material) = materials.get_mut(&self.handle) {
+            *material = self.material(hidden, color_scheme);
+        }
+    }
+
+    fn material(&self, hidden: bool, color_scheme: &ColorScheme) -> CubeMaterial {
+        let mut material = sub_cube_material(hidden, color_scheme);
+        material.cube_sides = self.cube_sides;
+        material.center_markers = self.center_markers && !hidden;
+        material
+    }
+}
+
+// bit mask of the outer faces of the sub cube in the same order
+// as the `CubeMaterial::colors`
+fn outer_faces_mask(side_size: u32, x: u32, y: u32, z: u32) -> u8 {
+    let last = side_size - 1;
+    [
+        z == 0,
+        y == 0,
+        x == 0,
+        false,
+        x == last,
+        y == last,
+        z == last,
+    ]
+    .into_iter()
+    .enumerate()
+    .fold(0, |mask, (i, outer)| mask | ((outer as u8) << i))
+}
+
+fn sub_cube_material(hidden: bool, color_scheme: &ColorScheme) -> CubeMaterial {
+    let mut colors = color_scheme.material_colors();
+    let mut pictures = color_scheme.material_pictures();
+    let mut patterns = color_scheme.material_patterns();
+    if hidden {
+        colors = [HIDDEN_STICKER_COLOR; 7];
+        pictures = Default::default();
+        patterns = Default::default();
+    }
+    let shape = color_scheme.sticker_shape.clamped();
+    let mut material = CubeMaterial {
+        colors,
+        patterns,
+        sticker_border: shape.border,
+        sticker_radius: shape.radius,
+        ..default()
+    };
+    material.set_pictures(pictures);
+    material
+}
+
+// transform of the sub cube that matches its position and orientation in the model
+fn sub_cube_transform(rb: &RubiksCube, cube: usize) -> Transform {
+    let coords = rb.centered_coords(rb.cube_to_pos[cube]);
+    Transform::from_translation(coords.as_vec3() * CUBE_SPACING / 2.0)
+        .with_rotation(rb.orientations[cube].to_quat())
+}
+
+pub fn snap_sub_cubes(rb: &RubiksCube, sub_cubes: &mut Query<(&SubCube, &mut Transform)>) {
+    for (sub_cube, mut transform) in sub_cubes.iter_mut() {
+        *transform = sub_cube_transform(rb, sub_cube.0);
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 fn init_rb(
+    game_settings: Res<GameSettings>,
+    game_states: Res<State<GameStates>>,
+    replay_viewer: Res<ReplayViewer>,
+    save_game: Res<SaveGame>,
+    color_scheme: Res<ColorScheme>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut cube_materials: ResMut<Assets<CubeMaterial>>,
 ) {
+    let cube_size = match (game_states.current(), &replay_viewer.replay) {
+        (GameStates::Replay, Some(replay)) => replay.cube_size,
+        // the trainer cases and the fewest moves challenge are 3x3x3 only
+        (GameStates::Trainer | GameStates::Fmc, _) => 3,
+        _ => save_game
+            .resumed_cube_size()
+            .unwrap_or(game_settings.cube_size),
+    };
+    let cube_sides = cube_size.clamp(MIN_CUBE_SIDES, MAX_CUBE_SIDES);
+    let mut rb = RubiksCube::new(cube_sides);
+    rb.supercube = save_game
+        .resumed_supercube()
+        .unwrap_or(game_settings.supercube);
+    let mut sub_cube_material = SubCubeMaterial {
+        center_markers: rb.supercube,
+        cube_sides,
+        ..default()
+    };
+    sub_cube_material.handle = cube_materials.add(sub_cube_material.material(false, &color_scheme));
     let sub_cube_mesh = meshes.add(Mesh::from(shape::Cube {
         size: CUBE_SIDE_SIZE,
     }));
-    let mut pos_to_cube = Vec::new();
+    let sub_cube_aabb = Aabb::from_min_max(
+        Vec3::splat(-CUBE_SIDE_SIZE / 2.0),
+        Vec3::splat(CUBE_SIDE_SIZE / 2.0),
+    );
     commands
         .spawn((
-            Transform::default(),
-            GlobalTransform::default(),
-            Visibility::default(),
-            ComputedVisibility::default(),
+            SpatialBundle::from_transform(Transform::from_scale(Vec3::splat(
+                REFERENCE_CUBE_SIDES / cube_sides as f32,
+            ))),
+            sub_cube_mesh,
+            InstancedSubCubes {
+                material: sub_cube_material.handle.clone(),
+            },
+            // the sub cubes move inside the cube and cast their own shadows
+            NoFrustumCulling,
+            NotShadowCaster,
         ))
         .with_children(|builder| {
-            let offset = match CUBE_SIDES % 2 {
-                0 => -CUBE_SPACING / 2.0 - (CUBE_SIDES - 1) as f32 / 2.0 * CUBE_SPACING,
-                1 => -(CUBE_SIDES as i32 / 2) as f32 * CUBE_SPACING,
-                _ => unreachable!(),
-            };
-            for x in 0..CUBE_SIDES {
-                for y in 0..CUBE_SIDES {
-                    for z in 0..CUBE_SIDES {
-                        // basically xyz represents a number in CUBE_SIDES base system and
+            for x in 0..cube_sides {
+                for y in 0..cube_sides {
+                    for z in 0..cube_sides {
+                        // interior cubes are never visible, so only the logical
+                        // model keeps track of them
+                        if !RubiksCube::is_surface_coords(cube_sides, x, y, z) {
+                            continue;
+                        }
+                        // basically xyz represents a number in cube_sides base system and
                         // index is dec representation of it
                         // so this index can be used as just id of a cube
                         // and as a mapping to the position of the qube
-                        let index = RubiksCube::corrds_to_pos(CUBE_SIDES, x, y, z);
-                        let material = cube_materials.add(CubeMaterial {
-                            colors: [
-                                if z != 0 { Color::BLACK } else { Color::WHITE },
-                                if y != 0 { Color::BLACK } else { Color::BLUE },
-                                if x != 0 {
-                                    Color::BLACK
-                                } else {
-                                    Color::rgb(1.0, 0.35, 0.0)
-                                },
-                                Color::NONE,
-                                if x != CUBE_SIDES - 1 {
-                                    Color::BLACK
-                                } else {
-                                    Color::RED
-                                },
-                                if y != CUBE_SIDES - 1 {
-                                    Color::BLACK
-                                } else {
-                                    Color::GREEN
-                                },
-                                if z != CUBE_SIDES - 1 {
-                                    Color::BLACK
-                                } else {
-                                    Color::YELLOW
-                                },
-                            ],
-                            ..default()
-                        });
+                        let index = RubiksCube::corrds_to_pos(cube_sides, x, y, z);
                         let entity = builder
-                            .spawn(MaterialMeshBundle::<CubeMaterial> {
-                                mesh: sub_cube_mesh.clone(),
-                                material: material.clone(),
-                                transform: Transform::from_xyz(
-                                    offset + x as f32 * CUBE_SPACING,
-                                    offset + y as f32 * CUBE_SPACING,
-                                    offset + z as f32 * CUBE_SPACING,
-                                ),
-                                ..default()
-                            })
-                            .insert(SubCube(index as usize))
+                            .spawn((
+                                SpatialBundle::from_transform(sub_cube_transform(
+                                    &rb,
+                                    index as usize,
+                                )),
+                                sub_cube_aabb.clone(),
+                                SubCube(index as usize),
+                                SubCubeInstance {
+                                    outer_faces: outer_faces_mask(cube_sides, x, y, z),
+                                },
+                            ))
                             .id();
-                        pos_to_cube.push((entity, index as usize));
+                        rb.pos_to_cube[index as usize].0 = Some(entity);
                     }
                 }
             }
         })
-        .insert(RubiksCube {
-            side_size: CUBE_SIDES,
-            pos_to_cube,
-            cube_to_pos: (0..CUBE_SIDES.pow(3)).collect(),
-        });
+        .insert((rb, MoveHistory::default()));
 
+    commands.insert_resource(sub_cube_material);
     commands.insert_resource(CurrentlyPointedAtSubCube::default());
     commands.insert_resource(CurrentlyPointedAtSubCubeRayNormal::default());
     commands.insert_resource(CurrentlySelectedSubCube::default());
+    commands.insert_resource(WholeCubeDrag::default());
     commands.insert_resource(CurrentlySelectedSubCubeRayNormal::default());
     commands.insert_resource(RotationAngle::default());
+    commands.insert_resource(MoveQueue::default());
+    commands.insert_resource(Scramble::default());
 }
 
 fn clean_rb(
-    rb_query: Query<Entity, With<RubiksCube>>,
-    sub_qubes_query: Query<(Entity, &Handle<Mesh>, &Handle<CubeMaterial>), With<SubCube>>,
+    rb_query: Query<(Entity, &Handle<Mesh>), With<RubiksCube>>,
+    sub_qubes_query: Query<Entity, With<SubCube>>,
+    sub_cube_material: Res<SubCubeMaterial>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<CubeMaterial>>,
 ) {
-    for (entity, mesh, material) in sub_qubes_query.iter() {
+    for entity in sub_qubes_query.iter() {
         commands.entity(entity).despawn();
-        meshes.remove(mesh);
-        materials.remove(material);
     }
+    materials.remove(&sub_cube_material.handle);
 
-    for entity in rb_query.iter() {
+    for (entity, mesh) in rb_query.iter() {
         commands.entity(entity).despawn();
+        meshes.remove(mesh);
     }
 
+    commands.remove_resource::<SubCubeMaterial>();
     commands.remove_resource::<CurrentlyPointedAtSubCube>();
     commands.remove_resource::<CurrentlyPointedAtSubCubeRayNormal>();
     commands.remove_resource::<CurrentlySelectedSubCube>();
+    commands.remove_resource::<WholeCubeDrag>();
     commands.remove_resource::<CurrentlySelectedSubCubeRayNormal>();
     commands.remove_resource::<RotationAngle>();
+    commands.remove_resource::<MoveQueue>();
+    commands.remove_resource::<Scramble>();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn selecting_sub_cube(
-    mouse_input: Res<Input<MouseButton>>,
+    action_input: ActionInput,
+    windows: Res<Windows>,
     cursor_ray: Res<CursorRay>,
-    mut query: Query<(Entity, &Aabb, &Transform, &mut Handle<CubeMaterial>), With<SubCube>>,
+    move_queue: Res<MoveQueue>,
+    query: Query<(Entity, &Aabb, &GlobalTransform), With<SubCube>>,
     mut currently_selected_sub_cube: ResMut<CurrentlySelectedSubCube>,
     mut currently_selected_sub_cube_normal: ResMut<CurrentlySelectedSubCubeRayNormal>,
+    mut whole_cube_drag: ResMut<WholeCubeDrag>,
+    mut egui_context: ResMut<EguiContext>,
 ) {
-    if mouse_input.just_pressed(MouseButton::Left) {
+    // clicks on the ui do not grab the cube behind it
+    if egui_context.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    // layers can not be dragged while queued turns are animated
+    if action_input.just_pressed(Action::TurnLayer) && move_queue.is_empty() {
         let mut closest = f32::MAX;
         let mut newly_selected = None;
-        for (entity, aabb, transform, _) in query.iter_mut() {
+        for (entity, aabb, transform) in query.iter() {
             if let Some([hit_near, _hit_far]) = cursor_ray
                 .0
                 .intersects_aabb(aabb, &transform.compute_matrix())
@@ -185,16 +438,123 @@ fn selecting_sub_cube(
 
         // sets intersection normal
         if let Some(entity) = newly_selected {
-            if let Ok((_, aabb, _, _)) = query.get(entity) {
+            if let Ok((_, aabb, _)) = query.get(entity) {
                 currently_selected_sub_cube_normal.0 =
                     Some(cursor_ray.0.aabb_plane_normal(closest, aabb));
             }
         }
 
         currently_selected_sub_cube.0 = newly_selected;
+
+        // dragging off the cube rotates the whole cube
+        if newly_selected.is_none() {
+            whole_cube_drag.0 = windows
+                .get_primary()
+                .and_then(|window| window.cursor_position())
+                .map(|start| CubeDrag {
+                    start,
+                    direction: None,
+                    angle: 0.0,
+                });
+        }
     }
 }
 
+fn rotate_whole_cube(
+    action_input: ActionInput,
+    windows: Res<Windows>,
+    camera: Query<&GlobalTransform, With<Camera>>,
+    mut whole_cube_drag: ResMut<WholeCubeDrag>,
+    mut rubiks_cube: Query<&mut Transform, With<RubiksCube>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if egui_context.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (Some(drag), Some(window), Ok(camera), Ok(mut transform)) = (
+        whole_cube_drag.0.as_mut(),
+        windows.get_primary(),
+        camera.get_single(),
+        rubiks_cube.get_single_mut(),
+    ) else {
+        return;
+    };
+    if !action_input.pressed(Action::TurnLayer) {
+        return;
+    }
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let delta = cursor - drag.start;
+
+    if drag.direction.is_none() && delta.length() > WHOLE_CUBE_DRAG_THRESHOLD {
+        // horizontal drags rotate around the camera up vector and vertical
+        // ones around its right vector, snapped to the closest cube axis
+        let (screen_direction, rotation_vector) = if delta.x.abs() > delta.y.abs() {
+            (Vec2::X, camera.up())
+        } else {
+            (Vec2::Y, -camera.right())
+        };
+        let axis = Axis::closest(rotation_vector).to_vec3();
+        let rotation_axis = axis * axis.dot(rotation_vector).signum();
+        drag.direction = Some((screen_direction, rotation_axis));
+    }
+
+    if let Some((screen_direction, rotation_axis)) = drag.direction {
+        // dragging across half of the window height is a quarter turn,
+        // a release commits at most one
+        drag.angle = (delta.dot(screen_direction) / window.height() * std::f32::consts::PI)
+            .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+        transform.rotation = Quat::from_axis_angle(rotation_axis, drag.angle);
+    }
+}
+
+fn stop_whole_cube_rotation(
+    action_input: ActionInput,
+    mut whole_cube_drag: ResMut<WholeCubeDrag>,
+    mut rubiks_cube: Query<(&mut RubiksCube, &mut MoveHistory, &mut Transform)>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform), Without<RubiksCube>>,
+    mut turn_events: EventWriter<TurnCommitted>,
+) {
+    if !action_input.just_released(Action::TurnLayer) {
+        return;
+    }
+    let Some(drag) = whole_cube_drag.0.take() else {
+        return;
+    };
+    let (Some((_, rotation_axis)), Ok((mut rb, mut history, mut transform))) =
+        (drag.direction, rubiks_cube.get_single_mut())
+    else {
+        return;
+    };
+
+    if drag.angle.abs() > std::f32::consts::FRAC_PI_4 {
+        let axis = Axis::closest(rotation_axis);
+        // positive angle around the positive axis is the prime rotation
+        let amount = if drag.angle * rotation_axis.dot(axis.to_vec3()) > 0.0 {
+            Amount::Prime
+        } else {
+            Amount::Single
+        };
+        let m = Move {
+            kind: MoveKind::Rotation(axis),
+            amount,
+        };
+        for turn in m.layer_turns(rb.side_size) {
+            rb.turn(&turn);
+            history.push(turn);
+            turn_events.send(TurnCommitted(QueuedTurn {
+                turn,
+                source: TurnSource::Player,
+            }));
+        }
+        for (sub_cube, mut transform) in sub_cubes.iter_mut() {
+            *transform = sub_cube_transform(&rb, sub_cube.0);
+        }
+    }
+    transform.rotation = Quat::IDENTITY;
+}
+
 fn rotate_side(
     rubiks_cube: Query<&RubiksCube>,
     currently_selected_sub_cube: Res<CurrentlySelectedSubCube>,
@@ -250,8 +610,9 @@ fn rotate_side(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn stop_rotation(
-    mouse_input: Res<Input<MouseButton>>,
+    action_input: ActionInput,
     rotation_angle: Res<RotationAngle>,
     game_audio: Res<GameSounds>,
     game_settings: Res<GameSettings>,
@@ -259,11 +620,17 @@ fn stop_rotation(
     mut currently_selected_sub_cube: ResMut<CurrentlySelectedSubCube>,
     mut currently_selected_sub_cube_normal: ResMut<CurrentlySelectedSubCubeRayNormal>,
     mut cursor_collinear_axis: ResMut<CursorCollinearAxis>,
-    mut rubiks_cube: Query<&mut RubiksCube>,
+    mut rubiks_cube: Query<(&mut RubiksCube, &mut MoveHistory)>,
     mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+    mut turn_events: EventWriter<TurnCommitted>,
 ) {
-    if mouse_input.just_released(MouseButton::Left) {
-        if let (Ok(mut rb), Some(selected_cube), Some(selected_sub_cube_normal), Some(direction)) = (
+    if action_input.just_released(Action::TurnLayer) {
+        if let (
+            Ok((mut rb, mut history)),
+            Some(selected_cube),
+            Some(selected_sub_cube_normal),
+            Some(direction),
+        ) = (
             rubiks_cube.get_single_mut(),
             currently_selected_sub_cube.0,
             currently_selected_sub_cube_normal.0,
@@ -273,33 +640,28 @@ fn stop_rotation(
                 RubiksCube::select_axis_and_rotation(selected_sub_cube_normal, direction);
             let sub_cube = sub_cubes.get_component::<SubCube>(selected_cube).unwrap();
             let cube_entities = rb.select_rotation_entities(sub_cube.0, rotation_axis);
-            let angle = if rotation_angle.0.abs() > std::f32::consts::FRAC_PI_4 {
-                // calculationg the remaining angle to rotate the layer
-                let angle = if rotation_angle.0.is_sign_positive() {
-                    std::f32::consts::FRAC_PI_2 - rotation_angle.0
+            if rotation_angle.0.abs() > std::f32::consts::FRAC_PI_4 {
+                // the layer can be dragged past the starting point, so the
+                // direction of the turn is taken from the final angle
+                let rotation = if rotation_angle.0.is_sign_positive() {
+                    Rotation::Ccw
                 } else {
-                    -std::f32::consts::FRAC_PI_2 - rotation_angle.0
+                    Rotation::Cw
                 };
-                rb.rotate(sub_cube.0, selected_sub_cube_normal, direction);
-                angle
-            } else {
-                -rotation_angle.0
-            };
+                let turn = rb.layer_turn(sub_cube.0, rotation_axis, rotation);
+                rb.turn(&turn);
+                history.push(turn);
+                turn_events.send(TurnCommitted(QueuedTurn {
+                    turn,
+                    source: TurnSource::Player,
+                }));
+            }
+            // the layer snaps either to the turned or to the starting position
             for entity in cube_entities {
-                let (_, mut transform) = sub_cubes
+                let (sub_cube, mut transform) = sub_cubes
                     .get_mut(entity)
                     .expect("Subcubes in rubiks cube should be in the query");
-                let rotation = if rotation_axis == Vec3::X {
-                    Quat::from_rotation_x(angle)
-                } else if rotation_axis == Vec3::Y {
-                    Quat::from_rotation_y(angle)
-                } else if rotation_axis == Vec3::Z {
-                    Quat::from_rotation_z(angle)
-                } else {
-                    println!("Got weired rotation axis: {rotation_axis}");
-                    return;
-                };
-                transform.rotate_around(Vec3::ZERO, rotation);
+                *transform = sub_cube_transform(&rb, sub_cube.0);
             }
         }
         currently_selected_sub_cube.0 = None;
@@ -311,3 +673,145 @@ fn stop_rotation(
             .with_volume(game_settings.volume);
     }
 }
+
+fn rubiks_cube_events(
+    currently_selected_sub_cube: Res<CurrentlySelectedSubCube>,
+    mut rubiks_cube_events: EventReader<RubiksCubeEvent>,
+    mut rubiks_cube: Query<(&mut RubiksCube, &mut MoveHistory)>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+    mut move_queue: ResMut<MoveQueue>,
+    mut scramble: ResMut<Scramble>,
+) {
+    for event in rubiks_cube_events.iter() {
+        // queued turns can not be mixed with the dragged one
+        if currently_selected_sub_cube.0.is_some() {
+            continue;
+        }
+        if let Ok((mut rb, mut history)) = rubiks_cube.get_single_mut() {
+            match event {
+                RubiksCubeEvent::Undo => {
+                    if let Some(turn) = history.undo() {
+                        move_queue.push(turn, TurnSource::Undo);
+                    }
+                }
+                RubiksCubeEvent::Redo => {
+                    if let Some(turn) = history.redo() {
+                        move_queue.push(turn, TurnSource::Redo);
+                    }
+                }
+                RubiksCubeEvent::Scramble => {
+                    // scrambles are applied to a solved cube and can not be undone
+                    move_queue.clear();
+                    rb.reset();
+                    snap_sub_cubes(&rb, &mut sub_cubes);
+                    *history = MoveHistory::default();
+                    scramble.0 = notation::scramble(rb.side_size);
+                    move_queue.extend(
+                        moves_layer_turns(&scramble.0, rb.side_size),
+                        TurnSource::Scramble,
+                    );
+                }
+                RubiksCubeEvent::SkipTurns => move_queue.skip_to_end(),
+                // turns made during the scramble would not be part of it
+                RubiksCubeEvent::Turn(_) if move_queue.is_scrambling() => {}
+                RubiksCubeEvent::Turn(m) => {
+                    for turn in m.layer_turns(rb.side_size) {
+                        history.push(turn);
+                        move_queue.push(turn, TurnSource::Player);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn ease_in_out(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn animate_move_queue(
+    time: Res<Time>,
+    game_audio: Res<GameSounds>,
+    game_settings: Res<GameSettings>,
+    audio: Res<Audio>,
+    mut move_queue: ResMut<MoveQueue>,
+    mut rubiks_cube: Query<&mut RubiksCube>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+    mut turn_events: EventWriter<TurnCommitted>,
+) {
+    if let Ok(mut rb) = rubiks_cube.get_single_mut() {
+        let mut delta = time.delta_seconds();
+        // time left after a finished turn is used by the next one,
+        // so short durations are not limited by the frame rate
+        while delta > 0.0 || move_queue.skip {
+            if move_queue.current.is_none() {
+                match move_queue.turns.pop_front() {
+                    Some(queued) => {
+                        if !move_queue.skip {
+                            audio
+                                .play(game_audio.rotation.clone())
+                                .with_volume(game_settings.volume);
+                        }
+                        // fast typing queues turns faster than they are
+                        // animated, so a backlog is played up to 4x faster
+                        let speedup = (move_queue.turns.len() + 1).min(4) as f32;
+                        move_queue.current = Some(AnimatedTurn {
+                            entities: rb.select_turn_entities(&queued.turn),
+                            queued,
+                            duration: (game_settings.turn_duration / speedup).max(f32::EPSILON),
+                            elapsed: 0.0,
+                            angle: 0.0,
+                        });
+                    }
+                    None => {
+                        move_queue.total = 0;
+                        move_queue.skip = false;
+                        break;
+                    }
+                }
+            }
+
+            let skip = move_queue.skip;
+            let animated = move_queue.current.as_mut().unwrap();
+            let duration = animated.duration;
+            let remaining = duration - animated.elapsed;
+            let step = if skip {
+                remaining
+            } else {
+                delta.min(remaining)
+            };
+            delta -= step;
+            animated.elapsed = if step < remaining {
+                animated.elapsed + step
+            } else {
+                duration
+            };
+
+            let t = (animated.elapsed / duration).min(1.0);
+            let turn = animated.queued.turn;
+            let angle = std::f32::consts::FRAC_PI_2 * turn.rotation.angle_sign() * ease_in_out(t);
+            let rotation = turn.quat(angle - animated.angle);
+            for entity in animated.entities.iter() {
+                let (_, mut transform) = sub_cubes
+                    .get_mut(*entity)
+                    .expect("Subcubes in rubiks cube should be in the query");
+                transform.rotate_around(Vec3::ZERO, rotation);
+            }
+            animated.angle = angle;
+
+            if t < 1.0 {
+                break;
+            }
+            rb.turn(&turn);
+            for entity in animated.entities.iter() {
+                let (sub_cube, mut transform) = sub_cubes
+                    .get_mut(*entity)
+                    .expect("Subcubes in rubiks cube should be in the query");
+                *transform = sub_cube_transform(&rb, sub_cube.0);
+            }
+            turn_events.send(TurnCommitted(animated.queued));
+            move_queue.current = None;
+        }
+    }
+}
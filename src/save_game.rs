@@ -0,0 +1,257 @@
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*, window::WindowCloseRequested};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blindfolded::{reset_blind_solve, BlindPhase, Blindfolded},
+    camera::OrbitCamera,
+    game_state::{reset_game_state, GameState},
+    notation::Move,
+    persistence,
+    replay::{Recording, ReplayRecording},
+    rubiks_cube::{CubeArrangement, MoveHistory, RubiksCube},
+    rubiks_cube_plugin::{snap_sub_cubes, Scramble, SubCube},
+    solve_timer::SolveTimer,
+    GameStates,
+};
+
+const SAVED_GAME_FILE: &str = "saved_game.ron";
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveGame {
+            saved: persistence::load(SAVED_GAME_FILE),
+            ..default()
+        });
+
+        app.add_system(autosave_on_close);
+        app.add_system_set(SystemSet::on_update(GameStates::InGame).with_system(resume_game));
+        app.add_system_set(
+            SystemSet::on_exit(GameStates::InGame).with_system(
+                save_on_exit
+                    .before(reset_game_state)
+                    .before(reset_blind_solve),
+            ),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SavedCamera {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub focus_point: Vec3,
+    pub radius: f32,
+}
+
+/// Game in progress with the timer and the replay recorded so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub cube: CubeArrangement,
+    pub history: MoveHistory,
+    pub scramble: Vec<Move>,
+    pub timer: SolveTimer,
+    pub recording: Option<Recording>,
+    pub camera: Option<SavedCamera>,
+    // the supercube setting may change before the game is resumed
+    #[serde(default)]
+    pub supercube: bool,
+    #[serde(default)]
+    pub blind_phase: BlindPhase,
+}
+
+impl SavedGame {
+    /// Timer to resume the game with. A finished solve was recorded before
+    /// the game was saved, so the timer starts over instead of finishing
+    /// it again.
+    pub fn resumed_timer(&self) -> SolveTimer {
+        match self.timer {
+            SolveTimer::Finished(_) => SolveTimer::Idle,
+            timer => timer,
+        }
+    }
+}
+
+/// The saved game, there is at most one. It is consumed when resumed.
+#[derive(Resource, Debug, Default)]
+pub struct SaveGame {
+    pub saved: Option<SavedGame>,
+    // the saved game is restored once the cube is spawned
+    pub resuming: bool,
+    // the game is saved when it is left
+    pub save_on_exit: bool,
+}
+
+impl SaveGame {
+    fn resumed(&self) -> Option<&SavedGame> {
+        self.saved.as_ref().filter(|_| self.resuming)
+    }
+
+    /// Size of the cube to spawn for the game being resumed.
+    pub fn resumed_cube_size(&self) -> Option<u32> {
+        self.resumed().map(|saved| saved.cube.side_size)
+    }
+
+    /// Whether the game being resumed is played on a supercube.
+    pub fn resumed_supercube(&self) -> Option<bool> {
+        self.resumed().map(|saved| saved.supercube)
+    }
+
+    fn store(&mut self, saved: SavedGame) {
+        persistence::save(SAVED_GAME_FILE, &saved);
+        self.saved = Some(saved);
+    }
+}
+
+#[derive(SystemParam)]
+struct CurrentGame<'w, 's> {
+    game_state: Res<'w, GameState>,
+    blindfolded: Res<'w, Blindfolded>,
+    scramble: Option<Res<'w, Scramble>>,
+    recording: Res<'w, ReplayRecording>,
+    rubiks_cube: Query<'w, 's, (&'static RubiksCube, &'static MoveHistory)>,
+    camera: Query<'w, 's, (&'static OrbitCamera, &'static Transform)>,
+}
+
+impl CurrentGame<'_, '_> {
+    fn save(&self) -> Option<SavedGame> {
+        let (rb, history) = self.rubiks_cube.get_single().ok()?;
+        Some(SavedGame {
+            cube: rb.arrangement(),
+            history: history.clone(),
+            scramble: self
+                .scramble
+                .as_ref()
+                .map_or_else(Vec::new, |scramble| scramble.0.clone()),
+            timer: self.game_state.timer,
+            recording: self.recording.0.clone(),
+            camera: self
+                .camera
+                .get_single()
+                .ok()
+                .map(|(orbit, transform)| SavedCamera {
+                    translation: transform.translation,
+                    rotation: transform.rotation,
+                    focus_point: orbit.focus_point,
+                    radius: orbit.radius,
+                }),
+            supercube: rb.supercube,
+            blind_phase: self.blindfolded.phase,
+        })
+    }
+}
+
+fn save_on_exit(current_game: CurrentGame, mut save_game: ResMut<SaveGame>) {
+    if !save_game.save_on_exit {
+        return;
+    }
+    save_game.save_on_exit = false;
+    if let Some(saved) = current_game.save() {
+        save_game.store(saved);
+    }
+}
+
+// closing the window in the middle of a game keeps it for later
+fn autosave_on_close(
+    game_states: Res<State<GameStates>>,
+    current_game: CurrentGame,
+    mut close_events: EventReader<WindowCloseRequested>,
+    mut exit_events: EventReader<AppExit>,
+    mut save_game: ResMut<SaveGame>,
+) {
+    let closing = close_events.iter().count() + exit_events.iter().count() > 0;
+    let in_game = *game_states.current() == GameStates::InGame
+        || game_states.inactives().contains(&GameStates::InGame);
+    if !closing || !in_game {
+        return;
+    }
+    if let Some(saved) = current_game.save() {
+        save_game.store(saved);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resume_game(
+    mut save_game: ResMut<SaveGame>,
+    mut game_state: ResMut<GameState>,
+    mut blindfolded: ResMut<Blindfolded>,
+    mut scramble: ResMut<Scramble>,
+    mut recording: ResMut<ReplayRecording>,
+    mut rubiks_cube: Query<(&mut RubiksCube, &mut MoveHistory)>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+    mut camera: Query<(&mut OrbitCamera, &mut Transform), Without<SubCube>>,
+) {
+    if !save_game.resuming {
+        return;
+    }
+    // the cube is spawned a frame after entering the state
+    let Ok((mut rb, mut history)) = rubiks_cube.get_single_mut() else {
+        return;
+    };
+    save_game.resuming = false;
+    // a resumed game is only saved again when it is left with a save
+    let Some(saved) = save_game.saved.take() else {
+        return;
+    };
+    persistence::remove(SAVED_GAME_FILE);
+    if !rb.restore(&saved.cube) {
+        warn!(
+            "The saved game does not fit a {0}x{0}x{0} cube",
+            rb.side_size
+        );
+        return;
+    }
+    snap_sub_cubes(&rb, &mut sub_cubes);
+    game_state.timer = saved.resumed_timer();
+    *history = saved.history;
+    scramble.0 = saved.scramble;
+    // a blindfolded solve goes on blindfolded
+    if saved.blind_phase != BlindPhase::Idle {
+        blindfolded.enabled = true;
+    }
+    blindfolded.phase = saved.blind_phase;
+    recording.0 = saved.recording;
+    if let (Some(saved), Ok((mut orbit, mut transform))) = (saved.camera, camera.get_single_mut()) {
+        orbit.focus_point = saved.focus_point;
+        orbit.radius = saved.radius;
+        transform.translation = saved.translation;
+        transform.rotation = saved.rotation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::solve_timer::{Penalty, SolveResult};
+
+    #[test]
+    fn resumed_timer() {
+        let mut saved = SavedGame {
+            cube: RubiksCube::new(3).arrangement(),
+            history: MoveHistory::default(),
+            scramble: Vec::new(),
+            timer: SolveTimer::Inspection(Duration::from_secs(3)),
+            recording: None,
+            camera: None,
+            supercube: false,
+            blind_phase: BlindPhase::Idle,
+        };
+        assert_eq!(saved.resumed_timer(), saved.timer);
+        saved.timer = SolveTimer::Solving {
+            time: Duration::from_secs(20),
+            penalty: Penalty::PlusTwo,
+        };
+        assert_eq!(saved.resumed_timer(), saved.timer);
+
+        // an inspection DNF is saved on the unsolved cube, resuming it
+        // must not record the solve again
+        saved.timer = SolveTimer::Finished(SolveResult {
+            time: Duration::ZERO,
+            penalty: Penalty::Dnf,
+        });
+        assert_eq!(saved.resumed_timer(), SolveTimer::Idle);
+    }
+}
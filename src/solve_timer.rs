@@ -0,0 +1,231 @@
+use std::{fmt::Display, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// WCA inspection time, exceeding it adds two seconds to the solve.
+pub const INSPECTION: Duration = Duration::from_secs(15);
+/// Exceeding the inspection by more than two seconds is a DNF.
+pub const INSPECTION_LIMIT: Duration = Duration::from_secs(17);
+const PLUS_TWO: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Penalty {
+    #[default]
+    None,
+    PlusTwo,
+    Dnf,
+}
+
+impl Penalty {
+    pub fn after_inspection(inspection: Duration) -> Self {
+        if inspection > INSPECTION_LIMIT {
+            Penalty::Dnf
+        } else if inspection > INSPECTION {
+            Penalty::PlusTwo
+        } else {
+            Penalty::None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SolveResult {
+    pub time: Duration,
+    pub penalty: Penalty,
+}
+
+impl SolveResult {
+    /// Time with the penalty applied, `None` for a DNF.
+    pub fn final_time(&self) -> Option<Duration> {
+        match self.penalty {
+            Penalty::None => Some(self.time),
+            Penalty::PlusTwo => Some(self.time + PLUS_TWO),
+            Penalty::Dnf => None,
+        }
+    }
+}
+
+impl Display for SolveResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.penalty {
+            Penalty::None => write!(f, "{}", format_duration(self.time)),
+            Penalty::PlusTwo => write!(f, "{}+", format_duration(self.time + PLUS_TWO)),
+            Penalty::Dnf => write!(f, "DNF({})", format_duration(self.time)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SolveTimer {
+    // no scrambled cube to solve
+    #[default]
+    Idle,
+    Inspection(Duration),
+    Solving {
+        time: Duration,
+        penalty: Penalty,
+    },
+    Finished(SolveResult),
+}
+
+impl SolveTimer {
+    pub fn start_inspection(&mut self) {
+        *self = SolveTimer::Inspection(Duration::ZERO);
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        match self {
+            SolveTimer::Inspection(inspection) => {
+                *inspection += delta;
+                // the solve can not be saved anymore
+                if *inspection > INSPECTION_LIMIT {
+                    *self = SolveTimer::Finished(SolveResult {
+                        time: Duration::ZERO,
+                        penalty: Penalty::Dnf,
+                    });
+                }
+            }
+            SolveTimer::Solving { time, .. } => *time += delta,
+            SolveTimer::Idle | SolveTimer::Finished(_) => {}
+        }
+    }
+
+    /// Called for every turn made by the player. Whole cube rotations
+    /// are allowed during the inspection and do not start the solve.
+    pub fn turn(&mut self, is_rotation: bool) {
+        if let SolveTimer::Inspection(inspection) = *self {
+            if !is_rotation {
+                *self = SolveTimer::Solving {
+                    time: Duration::ZERO,
+                    penalty: Penalty::after_inspection(inspection),
+                };
+            }
+        }
+    }
+
+    pub fn solved(&mut self) {
+        if let SolveTimer::Solving { time, penalty } = *self {
+            *self = SolveTimer::Finished(SolveResult { time, penalty });
+        }
+    }
+
+    pub fn result(&self) -> Option<SolveResult> {
+        match self {
+            SolveTimer::Finished(result) => Some(*result),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SolveTimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveTimer::Idle => write!(f, "Scramble to start"),
+            SolveTimer::Inspection(inspection) => match INSPECTION.checked_sub(*inspection) {
+                Some(left) => write!(f, "Inspection: {}", left.as_secs() + 1),
+                None => write!(f, "Inspection: +2"),
+            },
+            SolveTimer::Solving { time, .. } => write!(f, "{}", format_duration(*time)),
+            SolveTimer::Finished(result) => write!(f, "{result}"),
+        }
+    }
+}
+
+/// Formats times like "9.87" or "1:02.34".
+pub fn format_duration(duration: Duration) -> String {
+    let centis = duration.as_millis() / 10;
+    let (minutes, seconds, centis) = (centis / 6000, centis / 100 % 60, centis % 100);
+    if minutes > 0 {
+        format!("{minutes}:{seconds:02}.{centis:02}")
+    } else {
+        format!("{seconds}.{centis:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f32) -> Duration {
+        Duration::from_secs_f32(s)
+    }
+
+    #[test]
+    fn timer_solve() {
+        let mut timer = SolveTimer::default();
+        // turns before the scramble are not timed
+        timer.turn(false);
+        assert_eq!(timer, SolveTimer::Idle);
+
+        timer.start_inspection();
+        timer.tick(secs(5.0));
+        timer.turn(true);
+        assert!(matches!(timer, SolveTimer::Inspection(_)));
+
+        timer.turn(false);
+        timer.tick(secs(10.0));
+        timer.tick(secs(2.5));
+        timer.turn(false);
+        timer.solved();
+        let result = SolveTimer::Finished(SolveResult {
+            time: secs(12.5),
+            penalty: Penalty::None,
+        });
+        assert_eq!(timer, result);
+        assert_eq!(timer.result().unwrap().final_time(), Some(secs(12.5)));
+        // the result stays after the solve
+        timer.tick(secs(1.0));
+        assert_eq!(timer, result);
+    }
+
+    #[test]
+    fn timer_penalties() {
+        let mut timer = SolveTimer::default();
+        timer.start_inspection();
+        timer.tick(secs(16.0));
+        timer.turn(false);
+        timer.tick(secs(10.0));
+        timer.solved();
+        assert_eq!(
+            timer,
+            SolveTimer::Finished(SolveResult {
+                time: secs(10.0),
+                penalty: Penalty::PlusTwo,
+            })
+        );
+        assert_eq!(timer.result().unwrap().final_time(), Some(secs(12.0)));
+
+        timer.start_inspection();
+        timer.tick(secs(17.5));
+        assert_eq!(
+            timer,
+            SolveTimer::Finished(SolveResult {
+                time: Duration::ZERO,
+                penalty: Penalty::Dnf,
+            })
+        );
+        assert_eq!(timer.result().unwrap().final_time(), None);
+    }
+
+    #[test]
+    fn timer_format() {
+        assert_eq!(format_duration(Duration::from_millis(9876)), "9.87");
+        assert_eq!(format_duration(Duration::from_millis(62345)), "1:02.34");
+        let result = SolveResult {
+            time: secs(10.0),
+            penalty: Penalty::PlusTwo,
+        };
+        assert_eq!(result.to_string(), "12.00+");
+        let result = SolveResult {
+            time: secs(10.0),
+            penalty: Penalty::Dnf,
+        };
+        assert_eq!(result.to_string(), "DNF(10.00)");
+        let mut timer = SolveTimer::default();
+        timer.start_inspection();
+        timer.tick(secs(0.5));
+        assert_eq!(timer.to_string(), "Inspection: 15");
+        timer.tick(secs(15.0));
+        assert_eq!(timer.to_string(), "Inspection: +2");
+    }
+}
@@ -0,0 +1,401 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_state::{update_solve_timer, GameState, RecordSolve},
+    notation::Move,
+    persistence,
+    rubiks_cube::{MoveHistory, RubiksCube},
+    rubiks_cube_plugin::Scramble,
+    solve_timer::{format_duration, SolveResult},
+    GameStates,
+};
+
+const SOLVES_FILE: &str = "solves.ron";
+
+/// Sizes of the averages shown in the statistics.
+pub const AVERAGES: [usize; 3] = [5, 12, 100];
+
+pub struct StatisticsPlugin;
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(persistence::load::<Solves>(SOLVES_FILE).unwrap_or_default());
+
+        app.add_system_set(
+            SystemSet::on_update(GameStates::InGame)
+                .with_system(record_solve.label(RecordSolve).after(update_solve_timer)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Solve {
+    pub scramble: Vec<Move>,
+    pub result: SolveResult,
+    // layer turns without whole cube rotations
+    pub moves: u32,
+    // seconds since the unix epoch
+    pub date: u64,
+    pub cube_size: u32,
+    #[serde(default)]
+    pub session: u32,
+}
+
+/// Every completed solve, oldest first. The statistics are kept per
+/// session, the personal bests of a size span all of them.
+#[derive(Resource, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Solves {
+    pub solves: Vec<Solve>,
+    // new solves are recorded into this session
+    #[serde(default)]
+    pub session: u32,
+}
+
+impl Solves {
+    /// Sessions with solves and the current one, oldest first.
+    pub fn sessions(&self) -> Vec<u32> {
+        let mut sessions: Vec<u32> = self
+            .solves
+            .iter()
+            .map(|solve| solve.session)
+            .chain(std::iter::once(self.session))
+            .collect();
+        sessions.sort_unstable();
+        sessions.dedup();
+        sessions
+    }
+
+    /// Switches to a new session without solves.
+    pub fn start_session(&mut self) {
+        self.session = self.sessions().last().map_or(0, |last| last + 1);
+    }
+
+    /// Cube sizes solved in any session.
+    pub fn cube_sizes(&self) -> Vec<u32> {
+        let mut sizes: Vec<u32> = self.solves.iter().map(|solve| solve.cube_size).collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    }
+
+    /// Solves of the size in the current session.
+    pub fn of_size(&self, cube_size: u32) -> impl DoubleEndedIterator<Item = &Solve> {
+        self.in_session(self.session, cube_size)
+    }
+
+    fn in_session(&self, session: u32, cube_size: u32) -> impl DoubleEndedIterator<Item = &Solve> {
+        self.solves
+            .iter()
+            .filter(move |solve| solve.session == session && solve.cube_size == cube_size)
+    }
+
+    fn session_stats(&self, session: u32, cube_size: u32) -> SolveStats {
+        let times: Vec<Option<Duration>> = self
+            .in_session(session, cube_size)
+            .map(|solve| solve.result.final_time())
+            .collect();
+        SolveStats::new(&times)
+    }
+
+    /// Statistics of the size in the current session.
+    pub fn stats(&self, cube_size: u32) -> SolveStats {
+        self.session_stats(self.session, cube_size)
+    }
+
+    /// Best single and averages of the size over all sessions, the
+    /// averages do not span sessions.
+    pub fn personal_bests(&self, cube_size: u32) -> PersonalBests {
+        let sessions: Vec<SolveStats> = self
+            .sessions()
+            .into_iter()
+            .map(|session| self.session_stats(session, cube_size))
+            .collect();
+        PersonalBests {
+            single: sessions.iter().filter_map(|stats| stats.best).min(),
+            averages: (0..AVERAGES.len())
+                .map(|i| {
+                    sessions
+                        .iter()
+                        .filter_map(|stats| stats.averages[i].best)
+                        .min_by_key(|average| average.unwrap_or(Duration::MAX))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn save(&self) {
+        persistence::save(SOLVES_FILE, self);
+    }
+}
+
+/// Personal bests of a cube size.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PersonalBests {
+    pub single: Option<Duration>,
+    // in the order of `AVERAGES`, `None` if no session has enough solves
+    pub averages: Vec<Option<Option<Duration>>>,
+}
+
+/// Average of `n` solves, `None` if there are less solves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AverageStats {
+    pub n: usize,
+    pub current: Option<Option<Duration>>,
+    pub best: Option<Option<Duration>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SolveStats {
+    pub count: usize,
+    // times are `None` when there is no finished solve
+    pub mean: Option<Duration>,
+    pub best: Option<Duration>,
+    pub worst: Option<Duration>,
+    pub averages: Vec<AverageStats>,
+}
+
+impl SolveStats {
+    /// Statistics of final times, oldest first, `None` for DNF.
+    pub fn new(times: &[Option<Duration>]) -> Self {
+        let finished: Vec<Duration> = times.iter().flatten().copied().collect();
+        let mean = (!finished.is_empty())
+            .then(|| finished.iter().sum::<Duration>() / finished.len() as u32);
+        let averages = AVERAGES
+            .iter()
+            .map(|&n| AverageStats {
+                n,
+                current: times
+                    .len()
+                    .checked_sub(n)
+                    .map(|start| trimmed_average(&times[start..])),
+                best: times
+                    .windows(n)
+                    .map(trimmed_average)
+                    .min_by_key(|average| average.unwrap_or(Duration::MAX)),
+            })
+            .collect();
+        Self {
+            count: times.len(),
+            mean,
+            best: finished.iter().min().copied(),
+            worst: finished.iter().max().copied(),
+            averages,
+        }
+    }
+}
+
+/// WCA average: the best and worst 5% of the times (at least one each)
+/// are dropped and the rest is averaged. DNFs count as the worst times,
+/// so the average is a DNF if more of them are left than can be dropped.
+pub fn trimmed_average(times: &[Option<Duration>]) -> Option<Duration> {
+    let trim = times.len().div_ceil(20);
+    if times.len() <= 2 * trim {
+        return None;
+    }
+    let mut sorted: Vec<Duration> = times.iter().map(|t| t.unwrap_or(Duration::MAX)).collect();
+    sorted.sort_unstable();
+    let counted = &sorted[trim..sorted.len() - trim];
+    if counted.contains(&Duration::MAX) {
+        return None;
+    }
+    Some(counted.iter().sum::<Duration>() / counted.len() as u32)
+}
+
+/// Formats a time of the statistics, "DNF" for `None`.
+pub fn format_time(time: Option<Duration>) -> String {
+    time.map_or_else(|| "DNF".to_string(), format_duration)
+}
+
+/// Formats a unix timestamp as "YYYY-MM-DD" in UTC.
+pub fn format_date(date: u64) -> String {
+    // days to civil date from http://howardhinnant.github.io/date_algorithms.html
+    let z = (date / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn record_solve(
+    game_state: Res<GameState>,
+    scramble: Res<Scramble>,
+    rubiks_cube: Query<(&RubiksCube, &MoveHistory)>,
+    mut solves: ResMut<Solves>,
+    mut recorded: Local<bool>,
+) {
+    let Some(result) = game_state.timer.result() else {
+        *recorded = false;
+        return;
+    };
+    let Ok((rb, history)) = rubiks_cube.get_single() else {
+        return;
+    };
+    if *recorded {
+        return;
+    }
+    *recorded = true;
+
+    let session = solves.session;
+    solves.solves.push(Solve {
+        scramble: scramble.0.clone(),
+        result,
+        moves: history
+            .done
+            .iter()
+            .filter(|turn| !turn.is_rotation(rb.side_size))
+            .count() as u32,
+        date: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |date| date.as_secs()),
+        cube_size: rb.side_size,
+        session,
+    });
+    solves.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(secs: &[f32]) -> Vec<Option<Duration>> {
+        // negative times are DNFs
+        secs.iter()
+            .map(|&s| (s >= 0.0).then(|| Duration::from_secs_f32(s)))
+            .collect()
+    }
+
+    #[test]
+    fn average_trimming() {
+        assert_eq!(
+            trimmed_average(&times(&[10.0, 12.0, 11.0, 20.0, 5.0])),
+            Some(Duration::from_secs(11))
+        );
+        // one DNF is dropped as the worst time
+        assert_eq!(
+            trimmed_average(&times(&[10.0, 12.0, -1.0, 11.0, 5.0])),
+            Some(Duration::from_secs(11))
+        );
+        assert_eq!(
+            trimmed_average(&times(&[10.0, -1.0, -1.0, 11.0, 5.0])),
+            None
+        );
+
+        // 5 solves of 100 are dropped on each side
+        let mut hundred: Vec<f32> = vec![10.0; 90];
+        hundred.extend([1.0; 5]);
+        hundred.extend([-1.0; 5]);
+        assert_eq!(
+            trimmed_average(&times(&hundred)),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn solve_stats() {
+        let stats = SolveStats::new(&times(&[10.0, 8.0, -1.0, 12.0, 9.0, 7.0]));
+        assert_eq!(stats.count, 6);
+        assert_eq!(stats.best, Some(Duration::from_secs(7)));
+        assert_eq!(stats.worst, Some(Duration::from_secs(12)));
+        assert_eq!(stats.mean, Some(Duration::from_secs(46) / 5));
+
+        let ao5 = stats.averages[0];
+        assert_eq!(ao5.n, 5);
+        // 8 -1 12 9 7 -> 8 9 12
+        assert_eq!(ao5.current, Some(Some(Duration::from_secs(29) / 3)));
+        // 10 8 -1 12 9 -> 9 10 12
+        assert_eq!(ao5.best, Some(Some(Duration::from_secs(29) / 3)));
+
+        let ao12 = stats.averages[1];
+        assert_eq!(ao12.current, None);
+        assert_eq!(ao12.best, None);
+    }
+
+    #[test]
+    fn sessions() {
+        let solve = |secs: u64, session: u32| Solve {
+            scramble: Vec::new(),
+            result: SolveResult {
+                time: Duration::from_secs(secs),
+                penalty: crate::solve_timer::Penalty::None,
+            },
+            moves: 0,
+            date: 0,
+            cube_size: 3,
+            session,
+        };
+        let mut solves = Solves::default();
+        assert_eq!(solves.sessions(), [0]);
+        solves.solves.push(solve(10, 0));
+        solves.solves.push(solve(20, 0));
+
+        solves.start_session();
+        assert_eq!(solves.session, 1);
+        assert_eq!(solves.sessions(), [0, 1]);
+        assert_eq!(solves.stats(3).count, 0);
+        // sizes of the other sessions stay selectable for their bests
+        assert_eq!(solves.cube_sizes(), [3]);
+
+        solves.solves.push(solve(30, 1));
+        assert_eq!(solves.stats(3).best, Some(Duration::from_secs(30)));
+        // switching back shows the earlier solves only
+        solves.session = 0;
+        assert_eq!(solves.stats(3).count, 2);
+        assert_eq!(solves.stats(3).best, Some(Duration::from_secs(10)));
+        solves.start_session();
+        assert_eq!(solves.session, 2);
+    }
+
+    #[test]
+    fn personal_bests() {
+        let solve = |secs: u64, session: u32| Solve {
+            scramble: Vec::new(),
+            result: SolveResult {
+                time: Duration::from_secs(secs),
+                penalty: crate::solve_timer::Penalty::None,
+            },
+            moves: 0,
+            date: 0,
+            cube_size: 3,
+            session,
+        };
+        let mut solves = Solves::default();
+        assert_eq!(solves.personal_bests(3).single, None);
+        for secs in [20, 21, 22, 23, 24] {
+            solves.solves.push(solve(secs, 0));
+        }
+        for secs in [10, 30, 30, 30] {
+            solves.solves.push(solve(secs, 1));
+        }
+        solves.session = 1;
+
+        assert_eq!(solves.stats(3).best, Some(Duration::from_secs(10)));
+        let bests = solves.personal_bests(3);
+        assert_eq!(bests.single, Some(Duration::from_secs(10)));
+        // the only ao5 is in the first session
+        assert_eq!(bests.averages[0], Some(Some(Duration::from_secs(22))));
+        assert_eq!(bests.averages[1], None);
+        assert_eq!(solves.stats(3).averages[0].best, None);
+        assert_eq!(
+            solves.personal_bests(4),
+            PersonalBests {
+                single: None,
+                averages: vec![None; AVERAGES.len()],
+            }
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+    }
+}
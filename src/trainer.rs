@@ -0,0 +1,228 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cases::{step_solved, Case, Cases, AUFS},
+    persistence,
+    rubiks_cube::{MoveHistory, RubiksCube},
+    rubiks_cube_plugin::{snap_sub_cubes, MoveQueue, SubCube, TurnCommitted, TurnSource},
+    GameStates,
+};
+
+const TRAINER_FILE: &str = "trainer.ron";
+
+pub struct TrainerPlugin;
+
+impl Plugin for TrainerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(persistence::load::<Trainer>(TRAINER_FILE).unwrap_or_default());
+
+        app.add_system_set(
+            SystemSet::on_update(GameStates::Trainer)
+                .with_system(set_up_case)
+                .with_system(time_attempt.after(set_up_case)),
+        );
+        app.add_system_set(SystemSet::on_exit(GameStates::Trainer).with_system(stop_training));
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CaseStats {
+    pub attempts: u32,
+    pub solved: u32,
+    // sum of the times of the solved attempts
+    pub total_time: Duration,
+}
+
+impl CaseStats {
+    // solved attempts out of all, skipped cases count as misses
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.attempts > 0).then(|| self.solved as f32 / self.attempts as f32)
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        (self.solved > 0).then(|| self.total_time / self.solved)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    pub case: Case,
+    // time since the first turn that is not a rotation
+    pub time: Option<Duration>,
+}
+
+/// Cases picked for training and the results of every case, cases are
+/// referred to by name so the selection survives changes of the database.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Trainer {
+    pub selected: BTreeSet<String>,
+    pub stats: BTreeMap<String, CaseStats>,
+    #[serde(skip)]
+    pub attempt: Option<Attempt>,
+    // case and time of the previous attempt, `None` when it was skipped
+    #[serde(skip)]
+    pub previous: Option<(Case, Option<Duration>)>,
+}
+
+impl Trainer {
+    /// Random selected case, a different one than the previous if possible.
+    pub fn next_case(&self, cases: &Cases, rng: &fastrand::Rng) -> Option<Case> {
+        let previous = self.previous.as_ref().map(|(case, _)| &case.name);
+        let mut candidates: Vec<&Case> = cases
+            .0
+            .iter()
+            .filter(|case| self.selected.contains(&case.name))
+            .collect();
+        if candidates.len() > 1 {
+            candidates.retain(|case| Some(&case.name) != previous);
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[rng.usize(..candidates.len())].clone())
+    }
+
+    /// Ends the current attempt, solved when it has a time.
+    pub fn finish(&mut self, time: Option<Duration>) {
+        let Some(attempt) = self.attempt.take() else {
+            return;
+        };
+        let stats = self.stats.entry(attempt.case.name.clone()).or_default();
+        stats.attempts += 1;
+        if let Some(time) = time {
+            stats.solved += 1;
+            stats.total_time += time;
+        }
+        self.previous = Some((attempt.case, time));
+    }
+
+    pub fn save(&self) {
+        persistence::save(TRAINER_FILE, self);
+    }
+}
+
+// the next case is set up once the last turn of the previous one is animated
+fn set_up_case(
+    cases: Res<Cases>,
+    mut trainer: ResMut<Trainer>,
+    mut move_queue: ResMut<MoveQueue>,
+    mut rubiks_cube: Query<(&mut RubiksCube, &mut MoveHistory)>,
+    mut sub_cubes: Query<(&SubCube, &mut Transform)>,
+) {
+    if trainer.attempt.is_some() || !move_queue.is_empty() {
+        return;
+    }
+    // the cube is spawned a frame after entering the state
+    let Ok((mut rb, mut history)) = rubiks_cube.get_single_mut() else {
+        return;
+    };
+    let rng = fastrand::Rng::new();
+    let Some(case) = trainer.next_case(&cases, &rng) else {
+        return;
+    };
+
+    move_queue.clear();
+    *history = MoveHistory::default();
+    rb.reset();
+    for turn in case.setup_turns(rng.usize(..AUFS.len()), rng.usize(..AUFS.len())) {
+        rb.turn(&turn);
+    }
+    snap_sub_cubes(&rb, &mut sub_cubes);
+    trainer.attempt = Some(Attempt { case, time: None });
+}
+
+fn time_attempt(
+    time: Res<Time>,
+    mut trainer: ResMut<Trainer>,
+    rubiks_cube: Query<&RubiksCube>,
+    mut turn_events: EventReader<TurnCommitted>,
+) {
+    let Some(attempt) = trainer.attempt.as_mut() else {
+        turn_events.clear();
+        return;
+    };
+    if let Some(attempt_time) = attempt.time.as_mut() {
+        *attempt_time += time.delta();
+    }
+
+    let mut turned = false;
+    for TurnCommitted(queued) in turn_events.iter() {
+        if queued.source == TurnSource::Scramble {
+            continue;
+        }
+        turned = true;
+        if attempt.time.is_none() && !queued.turn.is_rotation(3) {
+            attempt.time = Some(Duration::ZERO);
+        }
+    }
+    let Ok(rb) = rubiks_cube.get_single() else {
+        return;
+    };
+    if turned && step_solved(rb, attempt.case.kind) {
+        let time = attempt.time.unwrap_or_default();
+        trainer.finish(Some(time));
+        trainer.save();
+    }
+}
+
+fn stop_training(mut trainer: ResMut<Trainer>) {
+    trainer.attempt = None;
+    trainer.previous = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cases::CaseKind;
+
+    #[test]
+    fn trainer_next_case() {
+        let cases = Cases::load();
+        let rng = fastrand::Rng::with_seed(1);
+        let mut trainer = Trainer::default();
+        assert_eq!(trainer.next_case(&cases, &rng), None);
+
+        trainer.selected.insert("T perm".to_string());
+        let case = trainer.next_case(&cases, &rng).unwrap();
+        assert_eq!(case.name, "T perm");
+        assert_eq!(case.kind, CaseKind::Pll);
+
+        // the only selected case repeats, otherwise cases alternate
+        trainer.previous = Some((case.clone(), None));
+        assert_eq!(trainer.next_case(&cases, &rng), Some(case));
+        trainer.selected.insert("H perm".to_string());
+        for _ in 0..10 {
+            let case = trainer.next_case(&cases, &rng).unwrap();
+            assert_eq!(case.name, "H perm");
+        }
+    }
+
+    #[test]
+    fn trainer_stats() {
+        let cases = Cases::load();
+        let sune = cases.0.iter().find(|case| case.name == "OLL 27 (Sune)");
+        let mut trainer = Trainer::default();
+        for time in [Some(2), None, Some(4)] {
+            trainer.attempt = Some(Attempt {
+                case: sune.unwrap().clone(),
+                time: None,
+            });
+            trainer.finish(time.map(Duration::from_secs));
+        }
+        // finishing without an attempt does nothing
+        trainer.finish(None);
+
+        let stats = trainer.stats["OLL 27 (Sune)"];
+        assert_eq!(stats.attempts, 3);
+        assert_eq!(stats.accuracy(), Some(2.0 / 3.0));
+        assert_eq!(stats.average(), Some(Duration::from_secs(3)));
+        assert_eq!(CaseStats::default().average(), None);
+        assert_eq!(trainer.attempt, None);
+    }
+}
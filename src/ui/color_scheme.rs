@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, ComboBox, Slider},
+    EguiContext,
+};
+
+use crate::color_scheme::{
+    ColorPreset, ColorScheme, StickerPattern, StickerShape, FACE_NAMES, PICTURE_THEMES_DIR,
+};
+
+pub fn show_color_scheme(
+    asset_server: Res<AssetServer>,
+    mut color_scheme: ResMut<ColorScheme>,
+    mut egui_context: ResMut<EguiContext>,
+    mut picture_themes: Local<Option<Vec<String>>>,
+) {
+    // the themes shipped in the assets do not change while the game runs
+    let picture_themes = picture_themes.get_or_insert_with(|| {
+        let mut themes: Vec<String> = asset_server
+            .asset_io()
+            .read_directory(Path::new(PICTURE_THEMES_DIR))
+            .map(|paths| {
+                paths
+                    .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        themes.sort();
+        themes
+    });
+    egui::Window::new("Colors")
+        .anchor(Align2::RIGHT_TOP, (-20.0, 20.0))
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            // the cube is recolored right away, so only real changes touch the resource
+            let mut scheme = color_scheme.clone();
+
+            let selected = scheme
+                .preset
+                .map_or_else(|| "Custom".to_string(), |preset| format!("{preset:?}"));
+            ComboBox::from_label("Preset")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for preset in ColorPreset::ALL {
+                        if ui
+                            .selectable_label(scheme.preset == Some(preset), format!("{preset:?}"))
+                            .clicked()
+                        {
+                            scheme.apply_preset(preset);
+                        }
+                    }
+                });
+
+            egui::Grid::new("face_colors").show(ui, |ui| {
+                let faces = scheme.faces.iter_mut().zip(scheme.patterns.iter_mut());
+                for (name, (color, pattern)) in FACE_NAMES.iter().zip(faces) {
+                    ui.label(*name);
+                    let [r, g, b, a] = color.as_rgba_f32().map(|c| (c * 255.0).round() as u8);
+                    let mut color32 = Color32::from_rgba_unmultiplied(r, g, b, a);
+                    if ui.color_edit_button_srgba(&mut color32).changed() {
+                        *color = Color::rgb_u8(color32.r(), color32.g(), color32.b());
+                        scheme.preset = None;
+                    }
+                    ComboBox::from_id_source(name)
+                        .selected_text(format!("{pattern:?}"))
+                        .show_ui(ui, |ui| {
+                            for value in StickerPattern::ALL {
+                                ui.selectable_value(pattern, value, format!("{value:?}"));
+                            }
+                        });
+                    ui.end_row();
+                }
+            });
+            ComboBox::from_label("Pictures")
+                .selected_text(scheme.picture_theme.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut scheme.picture_theme, None, "None");
+                    for theme in picture_themes.iter() {
+                        ui.selectable_value(
+                            &mut scheme.picture_theme,
+                            Some(theme.clone()),
+                            theme.as_str(),
+                        );
+                    }
+                });
+            if picture_themes.is_empty() {
+                ui.label(format!(
+                    "Picture themes go into assets/{PICTURE_THEMES_DIR}"
+                ));
+            }
+
+            let shape = &mut scheme.sticker_shape;
+            ui.add(
+                Slider::new(&mut shape.border, 0.0..=StickerShape::MAX_BORDER)
+                    .text("Sticker border"),
+            );
+            ui.add(
+                Slider::new(&mut shape.radius, 0.0..=StickerShape::MAX_RADIUS)
+                    .text("Corner radius"),
+            );
+
+            ui.horizontal(|ui| {
+                // symbols tell the faces apart when the colors do not
+                if ui.button("Distinct patterns").clicked() {
+                    scheme.patterns = StickerPattern::DISTINCT;
+                }
+                if ui.button("No patterns").clicked() {
+                    scheme.patterns = default();
+                }
+            });
+
+            if scheme != *color_scheme {
+                *color_scheme = scheme;
+            }
+        });
+}
+
+// dragging a color or a slider changes the scheme every frame, so it is
+// written once the settings are left
+pub fn save_color_scheme(color_scheme: Res<ColorScheme>) {
+    color_scheme.save();
+}
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, ScrollArea},
+    EguiContext,
+};
+
+use crate::input_bindings::{Binding, InputBindings};
+
+use super::UiStates;
+
+const MODIFIER_KEYS: [KeyCode; 4] = [
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LShift,
+    KeyCode::RShift,
+];
+
+#[derive(Default)]
+pub struct ControlsState {
+    // edited copy of the bindings, applied with the Apply button
+    bindings: Option<InputBindings>,
+    // index of the binding waiting for a new button
+    capturing: Option<usize>,
+}
+
+pub fn show_controls(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut input_bindings: ResMut<InputBindings>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+    mut state: Local<ControlsState>,
+) {
+    let ControlsState {
+        bindings,
+        capturing,
+    } = &mut *state;
+    let bindings = bindings.get_or_insert_with(|| input_bindings.clone());
+
+    if let Some(index) = *capturing {
+        if let Some(binding) = captured_binding(&keys, &mouse) {
+            bindings.0[index].1 = binding;
+            *capturing = None;
+        }
+    }
+
+    let mut back = false;
+    egui::Window::new("Controls")
+        .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(300.0);
+
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("bindings").striped(true).show(ui, |ui| {
+                    for index in 0..bindings.0.len() {
+                        let (action, binding) = bindings.0[index];
+                        ui.label(action.to_string());
+
+                        let text = if *capturing == Some(index) {
+                            "Press a button...".to_string()
+                        } else {
+                            binding.to_string()
+                        };
+                        if ui.button(text).clicked() {
+                            *capturing = Some(index);
+                        }
+
+                        if let Some(other) = bindings.conflict(index) {
+                            ui.colored_label(
+                                Color32::RED,
+                                format!("Also used by {}", bindings.0[other].0),
+                            );
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+            ui.horizontal(|ui| {
+                let conflicts = bindings.has_conflicts();
+                let apply = ui.add_enabled(
+                    !conflicts && *bindings != *input_bindings,
+                    egui::Button::new("Apply"),
+                );
+                if apply.clicked() {
+                    *input_bindings = bindings.clone();
+                    input_bindings.save();
+                }
+                if ui.button("Defaults").clicked() {
+                    *bindings = InputBindings::default();
+                    *capturing = None;
+                }
+                if ui.button("Back").clicked() {
+                    back = true;
+                }
+            });
+        });
+
+    if back {
+        ui_states.pop().unwrap();
+        *state = ControlsState::default();
+    }
+}
+
+// first button pressed this frame together with the held modifiers
+fn captured_binding(keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> Option<Binding> {
+    let binding = keys
+        .get_just_pressed()
+        .find(|key| !MODIFIER_KEYS.contains(key))
+        .map(|key| Binding::key(*key))
+        .or_else(|| {
+            mouse
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::mouse(*button))
+        })?;
+    Some(Binding {
+        ctrl: keys.any_pressed([KeyCode::LControl, KeyCode::RControl]),
+        shift: keys.any_pressed([KeyCode::LShift, KeyCode::RShift]),
+        ..binding
+    })
+}
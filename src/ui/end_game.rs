@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32},
+    EguiContext,
+};
+
+use crate::{
+    end_game::SolveSummary,
+    replay::{LastReplay, ReplayViewer},
+    rubiks_cube_plugin::RubiksCubeEvent,
+    solve_timer::format_duration,
+    statistics::Solves,
+    GameStates,
+};
+
+use super::UiStates;
+
+pub fn show_end_game(
+    solves: Res<Solves>,
+    last_replay: Res<LastReplay>,
+    mut replay_viewer: ResMut<ReplayViewer>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+    mut rubiks_cube_events: EventWriter<RubiksCubeEvent>,
+) {
+    egui::Window::new("Solved")
+        .anchor(Align2::CENTER_TOP, (0.0, 20.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(260.0);
+
+            ui.vertical_centered(|ui| {
+                ui.heading("Solved!");
+                if let Some(summary) = SolveSummary::of_last(&solves) {
+                    ui.heading(summary.result.to_string());
+                    if summary.is_personal_best() {
+                        ui.colored_label(Color32::GOLD, "New personal best!");
+                    }
+                }
+            });
+
+            if let Some(summary) = SolveSummary::of_last(&solves) {
+                egui::Grid::new("solve summary").show(ui, |ui| {
+                    ui.label("Cube");
+                    ui.label(format!("{0}x{0}x{0}", summary.cube_size));
+                    ui.end_row();
+                    ui.label("Moves");
+                    ui.label(summary.moves.to_string());
+                    ui.end_row();
+                    ui.label("TPS");
+                    ui.label(format!("{:.2}", summary.tps()));
+                    ui.end_row();
+                    if let Some(best) = summary.previous_best {
+                        ui.label("Previous best");
+                        ui.label(format_duration(best));
+                        ui.end_row();
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("New scramble").clicked() {
+                    game_states.pop().unwrap();
+                    ui_states.pop().unwrap();
+                    rubiks_cube_events.send(RubiksCubeEvent::Scramble);
+                }
+                if let Some(replay) = &last_replay.replay {
+                    // the replay needs its own cube, so the game is left
+                    if ui.button("Watch replay").clicked() {
+                        replay_viewer.open(replay.clone());
+                        game_states.replace(GameStates::Replay).unwrap();
+                        ui_states.replace(UiStates::Replay).unwrap();
+                    }
+                }
+                if ui.button("Main menu").clicked() {
+                    game_states.replace(GameStates::MainMenu).unwrap();
+                    ui_states.replace(UiStates::MainMenu).unwrap();
+                }
+            });
+        });
+}
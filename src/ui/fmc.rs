@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, Slider},
+    EguiContext,
+};
+
+use crate::{
+    fmc::{htm, Fmc, FmcResult, NissSolution},
+    notation::{format_moves, scramble},
+    solve_timer::format_duration,
+    GameStates,
+};
+
+use super::UiStates;
+
+pub fn show_fmc(
+    mut fmc: ResMut<Fmc>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Fewest moves")
+        .anchor(Align2::LEFT_TOP, (20.0, 20.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(320.0);
+
+            if !fmc.started {
+                let mut minutes = fmc.time_limit.as_secs() / 60;
+                ui.add(Slider::new(&mut minutes, 5..=120).text("Time limit in minutes"));
+                fmc.time_limit = Duration::from_secs(minutes * 60);
+                ui.horizontal(|ui| {
+                    if ui.button("Start").clicked() {
+                        fmc.start_clock();
+                    }
+                    if ui.button("Main menu").clicked() {
+                        game_states.pop().unwrap();
+                        ui_states.pop().unwrap();
+                    }
+                });
+                return;
+            }
+
+            ui.heading(match fmc.result {
+                Some(result) => result.to_string(),
+                None => format_duration(fmc.time_left),
+            });
+            ui.label("Scramble");
+            ui.monospace(format_moves(&fmc.scramble));
+
+            ui.separator();
+            let editable = fmc.result.is_none();
+            ui.add_enabled(
+                editable,
+                egui::TextEdit::multiline(&mut fmc.solution)
+                    .desired_rows(6)
+                    .hint_text("R U F' (inverse moves) // comment"),
+            );
+            match NissSolution::parse(&fmc.solution) {
+                Ok(solution) => {
+                    ui.label(format!("{} HTM", htm(&solution.moves())));
+                }
+                Err(error) => {
+                    ui.colored_label(Color32::RED, error.to_string());
+                }
+            }
+
+            ui.horizontal(|ui| {
+                let inverse = ui.checkbox(&mut fmc.inverse_view, "Inverse scramble");
+                if ui.button("Apply to cube").clicked() || inverse.changed() {
+                    fmc.sandbox_outdated = true;
+                }
+            });
+
+            ui.separator();
+            if fmc.result == Some(FmcResult::Dnf) {
+                ui.label("The solution does not solve the scramble");
+            }
+            ui.horizontal(|ui| {
+                if editable {
+                    if ui.button("Submit").clicked() {
+                        fmc.submit();
+                    }
+                } else if ui.button("New scramble").clicked() {
+                    fmc.start(scramble(3));
+                }
+                if ui.button("Main menu").clicked() {
+                    game_states.pop().unwrap();
+                    ui_states.pop().unwrap();
+                }
+            });
+        });
+}
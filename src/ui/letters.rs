@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, Color32, FontId, LayerId},
+    EguiContext,
+};
+
+use crate::{
+    blindfolded::Blindfolded,
+    camera::OrbitCamera,
+    letters::{letter_pairs, trace_memo, LetterScheme},
+    rubiks_cube::{RubiksCube, FACE_NORMALS},
+    rubiks_cube_plugin::CUBE_SIDE_SIZE,
+};
+
+const LETTER_SIZE: f32 = 16.0;
+
+pub fn show_letters(
+    blindfolded: Res<Blindfolded>,
+    rubiks_cube: Query<&RubiksCube>,
+    mut scheme: ResMut<LetterScheme>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let Ok(rb) = rubiks_cube.get_single() else {
+        return;
+    };
+    if rb.side_size != 3 {
+        return;
+    }
+    egui::Window::new("Letters")
+        .anchor(Align2::LEFT_TOP, (20.0, 20.0))
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(240.0);
+
+            let mut changed = ui.checkbox(&mut scheme.show, "Show letters").changed();
+            // tracing the hidden cube would give the memo away
+            if blindfolded.phase.stickers_hidden() {
+                ui.label("Memo is hidden during the execution");
+            } else if let Some(memo) = trace_memo(rb, &scheme) {
+                egui::Grid::new("memo").show(ui, |ui| {
+                    ui.label("Edges");
+                    ui.label(letter_pairs(&memo.edges));
+                    ui.end_row();
+                    ui.label("Corners");
+                    ui.label(letter_pairs(&memo.corners));
+                    ui.end_row();
+                });
+                if memo.parity() {
+                    ui.label("Parity");
+                }
+            }
+
+            ui.collapsing("Scheme", |ui| {
+                ui.label("Letters in the Speffz order: U, L, F, R, B, D faces, clockwise");
+                egui::Grid::new("scheme").show(ui, |ui| {
+                    // the letters are saved once a field is left, not on every key
+                    ui.label("Corners");
+                    changed |= ui.text_edit_singleline(&mut scheme.corners).lost_focus();
+                    ui.end_row();
+                    ui.label("Edges");
+                    changed |= ui.text_edit_singleline(&mut scheme.edges).lost_focus();
+                    ui.end_row();
+                });
+                if !scheme.is_valid() {
+                    ui.colored_label(Color32::RED, "Each needs 24 letters");
+                }
+                if ui.button("Speffz").clicked() {
+                    *scheme = LetterScheme {
+                        show: scheme.show,
+                        ..default()
+                    };
+                    changed = true;
+                }
+            });
+            if changed {
+                scheme.save();
+            }
+        });
+}
+
+/// Draws the letters of the stickers facing the camera over the cube, the
+/// letters move with the stickers.
+pub fn show_sticker_letters(
+    scheme: Res<LetterScheme>,
+    blindfolded: Res<Blindfolded>,
+    rubiks_cube: Query<&RubiksCube>,
+    sub_cubes: Query<&GlobalTransform>,
+    camera: Query<(&Camera, &GlobalTransform), With<OrbitCamera>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    if !scheme.show || blindfolded.phase.stickers_hidden() {
+        return;
+    }
+    let (Ok(rb), Ok((camera, camera_transform))) = (rubiks_cube.get_single(), camera.get_single())
+    else {
+        return;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+    if rb.side_size != 3 {
+        return;
+    }
+    let painter = egui_context.ctx_mut().layer_painter(LayerId::background());
+    for (pos, (entity, cube)) in rb.pos_to_cube.iter().enumerate() {
+        let Some(transform) = entity.and_then(|entity| sub_cubes.get(entity).ok()) else {
+            continue;
+        };
+        let offset = rb.centered_coords(pos as u32) / 2;
+        let home = rb.centered_coords(*cube as u32) / 2;
+        for normal in FACE_NORMALS {
+            if normal.dot(offset) != 1 {
+                continue;
+            }
+            let sticker = rb.sticker(pos as u32, normal);
+            let Some(letter) = scheme.sticker_letter(sticker, home) else {
+                continue;
+            };
+            let local = sticker.as_vec3() * CUBE_SIDE_SIZE / 2.0;
+            let center = transform.transform_point(local);
+            let facing = transform.affine().transform_vector3(local);
+            if facing.dot(camera_transform.translation() - center) <= 0.0 {
+                continue;
+            }
+            let Some(screen) = camera.world_to_viewport(camera_transform, center) else {
+                continue;
+            };
+            // viewport coordinates start at the bottom left
+            painter.text(
+                egui::pos2(screen.x, viewport_size.y - screen.y),
+                Align2::CENTER_CENTER,
+                letter,
+                FontId::proportional(LETTER_SIZE),
+                Color32::BLACK,
+            );
+        }
+    }
+}
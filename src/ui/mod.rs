@@ -1,12 +1,33 @@
+mod color_scheme;
+mod controls;
+mod end_game;
+mod fmc;
+mod letters;
+mod replays;
+mod statistics;
+mod trainer;
+
 use bevy::{app::AppExit, prelude::*};
 use bevy_egui::{
-    egui::{self, Align2, ComboBox, Slider},
+    egui::{self, Align2, Checkbox, ComboBox, ProgressBar, Slider},
     EguiContext, EguiPlugin,
 };
 
 use crate::{
-    game_settings::{GameSettings, GameSettingsEvent},
+    blindfolded::{BlindPhase, Blindfolded},
+    game_settings::{
+        GameSettings, GameSettingsEvent, MAX_UI_SCALE, MIN_UI_SCALE, MSAA_SAMPLES, RESOLUTIONS,
+        WINDOW_MODES,
+    },
     game_state::GameState,
+    input_bindings::{Action, ActionInput},
+    last_layer::LastLayerCase,
+    notation::format_moves,
+    replay::LastReplay,
+    rubiks_cube::RubiksCube,
+    rubiks_cube_plugin::{MoveQueue, RubiksCubeEvent, Scramble, MAX_CUBE_SIDES, MIN_CUBE_SIDES},
+    save_game::SaveGame,
+    solve_timer::{format_duration, SolveTimer},
     GameStates,
 };
 
@@ -15,6 +36,14 @@ pub enum UiStates {
     MainMenu,
     InGame,
     Settings,
+    Controls,
+    Statistics,
+    Replays,
+    Replay,
+    TrainerCases,
+    Trainer,
+    Fmc,
+    EndGame,
     Paused,
 }
 
@@ -26,6 +55,39 @@ impl Plugin for UiPlugin {
         app.add_state(UiStates::MainMenu);
 
         app.add_system(game_ui);
+        app.add_system_set(
+            SystemSet::on_update(UiStates::InGame)
+                .with_system(letters::show_letters)
+                .with_system(letters::show_sticker_letters),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::Settings).with_system(color_scheme::show_color_scheme),
+        );
+        app.add_system_set(
+            SystemSet::on_exit(UiStates::Settings).with_system(color_scheme::save_color_scheme),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::Controls).with_system(controls::show_controls),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::Replays).with_system(replays::show_replays),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::Replay).with_system(replays::show_replay_player),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::Statistics).with_system(statistics::show_statistics),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::TrainerCases).with_system(trainer::show_trainer_cases),
+        );
+        app.add_system_set(
+            SystemSet::on_update(UiStates::Trainer).with_system(trainer::show_trainer),
+        );
+        app.add_system_set(SystemSet::on_update(UiStates::Fmc).with_system(fmc::show_fmc));
+        app.add_system_set(
+            SystemSet::on_update(UiStates::EndGame).with_system(end_game::show_end_game),
+        );
 
         app.add_system_set(
             SystemSet::on_update(GameStates::InGame).with_system(game_keyboard_actins),
@@ -34,34 +96,62 @@ impl Plugin for UiPlugin {
 }
 
 fn game_keyboard_actins(
-    keys: Res<Input<KeyCode>>,
+    action_input: ActionInput,
     mut game_states: ResMut<State<GameStates>>,
     mut ui_states: ResMut<State<UiStates>>,
+    mut rubiks_cube_events: EventWriter<RubiksCubeEvent>,
 ) {
-    if keys.just_pressed(KeyCode::Escape) {
+    if action_input.just_pressed(Action::Pause) {
         game_states.push(GameStates::Paused).unwrap();
         ui_states.push(UiStates::Paused).unwrap();
     }
+
+    if action_input.just_pressed(Action::Undo) {
+        rubiks_cube_events.send(RubiksCubeEvent::Undo);
+    }
+    if action_input.just_pressed(Action::Redo) {
+        rubiks_cube_events.send(RubiksCubeEvent::Redo);
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn game_ui(
     game_state: Res<GameState>,
     game_settings: Res<GameSettings>,
+    last_replay: Res<LastReplay>,
+    last_layer_case: Res<LastLayerCase>,
+    move_queue: Option<Res<MoveQueue>>,
+    scramble: Option<Res<Scramble>>,
+    rubiks_cube: Query<&RubiksCube>,
+    mut blindfolded: ResMut<Blindfolded>,
+    mut save_game: ResMut<SaveGame>,
     mut game_states: ResMut<State<GameStates>>,
     mut ui_states: ResMut<State<UiStates>>,
     mut egui_context: ResMut<EguiContext>,
     mut exit_event: EventWriter<AppExit>,
     mut settings_events: EventWriter<GameSettingsEvent>,
-    mut local_settings: Local<GameSettings>,
+    mut rubiks_cube_events: EventWriter<RubiksCubeEvent>,
+    mut local_settings: Local<Option<GameSettings>>,
 ) {
     match ui_states.current() {
         UiStates::MainMenu => show_main_menu(
+            &mut save_game,
             &mut game_states,
             &mut ui_states,
             &mut egui_context,
             &mut exit_event,
         ),
-        UiStates::InGame => show_in_game(&game_state, &mut egui_context),
+        UiStates::InGame => show_in_game(
+            &game_state,
+            &last_replay,
+            &last_layer_case,
+            move_queue.as_deref(),
+            scramble.as_deref(),
+            rubiks_cube.get_single().ok(),
+            &mut blindfolded,
+            &mut egui_context,
+            &mut rubiks_cube_events,
+        ),
         UiStates::Settings => show_settings(
             &game_settings,
             &mut ui_states,
@@ -69,11 +159,26 @@ fn game_ui(
             &mut settings_events,
             &mut local_settings,
         ),
-        UiStates::Paused => show_paused(&mut game_states, &mut ui_states, &mut egui_context),
+        // drawn by their own systems
+        UiStates::Controls
+        | UiStates::Statistics
+        | UiStates::Replays
+        | UiStates::Replay
+        | UiStates::TrainerCases
+        | UiStates::Trainer
+        | UiStates::Fmc
+        | UiStates::EndGame => {}
+        UiStates::Paused => show_paused(
+            &mut save_game,
+            &mut game_states,
+            &mut ui_states,
+            &mut egui_context,
+        ),
     }
 }
 
 fn show_main_menu(
+    save_game: &mut SaveGame,
     game_states: &mut ResMut<State<GameStates>>,
     ui_states: &mut ResMut<State<UiStates>>,
     egui_context: &mut ResMut<EguiContext>,
@@ -87,8 +192,17 @@ fn show_main_menu(
             ui.set_width(200.0);
             ui.set_height(200.0);
 
+            if save_game.saved.is_some() && ui.button("Continue").clicked() {
+                save_game.resuming = true;
+                game_states.push(GameStates::InGame).unwrap();
+                ui_states.push(UiStates::InGame).unwrap();
+            }
             let play = ui.button("Play");
+            let trainer = ui.button("Trainer");
+            let fmc = ui.button("Fewest moves");
             let settings = ui.button("Settings");
+            let statistics = ui.button("Statistics");
+            let replays = ui.button("Replays");
             let exit = ui.button("Exit");
 
             if play.clicked() {
@@ -96,16 +210,40 @@ fn show_main_menu(
                 ui_states.push(UiStates::InGame).unwrap();
             }
 
+            if trainer.clicked() {
+                ui_states.push(UiStates::TrainerCases).unwrap();
+            }
+            if fmc.clicked() {
+                game_states.push(GameStates::Fmc).unwrap();
+                ui_states.push(UiStates::Fmc).unwrap();
+            }
             if settings.clicked() {
                 ui_states.push(UiStates::Settings).unwrap();
             }
+            if statistics.clicked() {
+                ui_states.push(UiStates::Statistics).unwrap();
+            }
+            if replays.clicked() {
+                ui_states.push(UiStates::Replays).unwrap();
+            }
             if exit.clicked() {
                 exit_event.send(AppExit);
             }
         });
 }
 
-fn show_in_game(game_state: &Res<GameState>, egui_context: &mut ResMut<EguiContext>) {
+#[allow(clippy::too_many_arguments)]
+fn show_in_game(
+    game_state: &Res<GameState>,
+    last_replay: &LastReplay,
+    last_layer_case: &LastLayerCase,
+    move_queue: Option<&MoveQueue>,
+    scramble: Option<&Scramble>,
+    rubiks_cube: Option<&RubiksCube>,
+    blindfolded: &mut Blindfolded,
+    egui_context: &mut ResMut<EguiContext>,
+    rubiks_cube_events: &mut EventWriter<RubiksCubeEvent>,
+) {
     egui::Window::new("Rubik's Cube")
         .anchor(Align2::CENTER_TOP, (0.0, 20.0))
         .title_bar(false)
@@ -114,48 +252,176 @@ fn show_in_game(game_state: &Res<GameState>, egui_context: &mut ResMut<EguiConte
             ui.set_width(200.0);
             ui.set_height(20.0);
             ui.label(format!("Solved: {}", game_state.is_solved));
+            if let Some(rb) = rubiks_cube.filter(|rb| rb.supercube) {
+                let twisted = rb
+                    .center_twists()
+                    .iter()
+                    .filter(|(_, twist)| *twist != Some(0))
+                    .count();
+                ui.label(format!("Twisted centers: {twisted}"));
+            }
+            if blindfolded.enabled {
+                ui.heading(blindfolded.phase.to_string());
+                show_blind_phase(ui, blindfolded, rubiks_cube);
+            } else {
+                ui.heading(game_state.timer.to_string());
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Undo").clicked() {
+                    rubiks_cube_events.send(RubiksCubeEvent::Undo);
+                }
+                if ui.button("Redo").clicked() {
+                    rubiks_cube_events.send(RubiksCubeEvent::Redo);
+                }
+                if ui.button("Scramble").clicked() {
+                    rubiks_cube_events.send(RubiksCubeEvent::Scramble);
+                }
+            });
+
+            if let Some(move_queue) = move_queue.filter(|queue| !queue.is_empty()) {
+                let (done, total) = move_queue.progress();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        ProgressBar::new(done as f32 / total as f32)
+                            .desired_width(140.0)
+                            .text(format!("{done}/{total}")),
+                    );
+                    if ui.button("Skip").clicked() {
+                        rubiks_cube_events.send(RubiksCubeEvent::SkipTurns);
+                    }
+                });
+            }
+
+            if let Some(scramble) = scramble.filter(|scramble| !scramble.0.is_empty()) {
+                ui.collapsing("Scramble", |ui| {
+                    ui.label(format_moves(&scramble.0));
+                });
+            }
+
+            // a case name would give away the hidden stickers
+            if let Some(recognition) = last_layer_case.0.as_ref().filter(|_| !blindfolded.enabled) {
+                ui.label(&recognition.case.name);
+                let setup = format_moves(&recognition.setup);
+                ui.small(format!("{setup} {}", recognition.case.algorithm).trim());
+            }
+
+            if let Some(splits) = &last_replay.splits {
+                ui.collapsing("Splits", |ui| replays::splits_grid(ui, splits));
+            }
+
+            // switching modes mid solve would mix up the timers
+            let can_switch = matches!(game_state.timer, SolveTimer::Idle | SolveTimer::Finished(_))
+                && !blindfolded.phase.stickers_hidden();
+            ui.add_enabled(
+                can_switch,
+                Checkbox::new(&mut blindfolded.enabled, "Blindfolded"),
+            );
         });
 }
 
+fn show_blind_phase(ui: &mut egui::Ui, blindfolded: &mut Blindfolded, rb: Option<&RubiksCube>) {
+    match blindfolded.phase {
+        BlindPhase::Memo(_) => {
+            if ui.button("Start solving").clicked() {
+                blindfolded.phase.start_execution();
+            }
+        }
+        BlindPhase::Execution { .. } => {
+            if ui.button("Give up").clicked() {
+                let pieces_off = rb.map_or(0, RubiksCube::pieces_off);
+                blindfolded.phase.finish(pieces_off);
+            }
+        }
+        BlindPhase::Finished(result) => {
+            ui.label(format!(
+                "Memo {}, execution {}",
+                format_duration(result.memo),
+                format_duration(result.execution)
+            ));
+        }
+        BlindPhase::Idle => {}
+    }
+}
+
 fn show_settings(
     game_settings: &Res<GameSettings>,
     ui_states: &mut ResMut<State<UiStates>>,
     egui_context: &mut ResMut<EguiContext>,
     settings_events: &mut EventWriter<GameSettingsEvent>,
-    local_settings: &mut Local<GameSettings>,
+    local_settings: &mut Local<Option<GameSettings>>,
 ) {
+    // the settings are edited on a copy until they are applied
+    let local_settings = local_settings.get_or_insert(**game_settings);
     egui::Window::new("Rubik's Cube")
         .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
         .title_bar(false)
         .resizable(false)
         .show(egui_context.ctx_mut(), |ui| {
             ui.set_width(200.0);
-            ui.set_height(200.0);
 
             let mode = &mut local_settings.mode;
             ComboBox::from_label("WindowMode")
                 .selected_text(format!("{mode:?}"))
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(mode, WindowMode::Windowed, "Windowed");
-                    ui.selectable_value(mode, WindowMode::Fullscreen, "Fullscreen");
+                    for value in WINDOW_MODES {
+                        ui.selectable_value(mode, value, format!("{value:?}"));
+                    }
                 });
+            let resolution = &mut local_settings.resolution;
+            ComboBox::from_label("Resolution")
+                .selected_text(format!("{}x{}", resolution.0, resolution.1))
+                .show_ui(ui, |ui| {
+                    for value in RESOLUTIONS {
+                        ui.selectable_value(resolution, value, format!("{}x{}", value.0, value.1));
+                    }
+                });
+            ui.add(Checkbox::new(&mut local_settings.vsync, "VSync"));
+            let samples = &mut local_settings.msaa_samples;
+            ComboBox::from_label("MSAA")
+                .selected_text(format!("{samples}x"))
+                .show_ui(ui, |ui| {
+                    for value in MSAA_SAMPLES {
+                        ui.selectable_value(samples, value, format!("{value}x"));
+                    }
+                });
+            ui.add(Checkbox::new(&mut local_settings.shadows, "Shadows"));
+            ui.add(Checkbox::new(&mut local_settings.supercube, "Supercube"))
+                .on_hover_text("Centers have to be turned back too, for picture cubes");
+            ui.add(
+                Slider::new(&mut local_settings.ui_scale, MIN_UI_SCALE..=MAX_UI_SCALE)
+                    .step_by(0.25)
+                    .text("UI scale"),
+            );
 
             ui.add(Slider::new(&mut local_settings.volume, 0.0..=10.0).text("Volume"));
+            ui.add(
+                Slider::new(
+                    &mut local_settings.cube_size,
+                    MIN_CUBE_SIDES..=MAX_CUBE_SIDES,
+                )
+                .text("Cube size"),
+            );
+
+            if ui.button("Controls").clicked() {
+                ui_states.push(UiStates::Controls).unwrap();
+            }
 
             let apply = ui.button("Apply");
-            if apply.clicked() && **local_settings != **game_settings {
-                settings_events.send(GameSettingsEvent::Apply(**local_settings));
+            if apply.clicked() && *local_settings != **game_settings {
+                settings_events.send(GameSettingsEvent::Apply(*local_settings));
             }
 
             let back = ui.button("Back");
             if back.clicked() {
                 ui_states.pop().unwrap();
-                **local_settings = **game_settings;
+                *local_settings = **game_settings;
             }
         });
 }
 
 fn show_paused(
+    save_game: &mut SaveGame,
     game_states: &mut ResMut<State<GameStates>>,
     ui_states: &mut ResMut<State<UiStates>>,
     egui_context: &mut ResMut<EguiContext>,
@@ -170,6 +436,8 @@ fn show_paused(
 
             let resume = ui.button("Resume");
             let settings = ui.button("Settings");
+            let statistics = ui.button("Statistics");
+            let save_and_quit = ui.button("Save and quit");
             let main_menu = ui.button("Main menu");
 
             if resume.clicked() {
@@ -180,7 +448,13 @@ fn show_paused(
             if settings.clicked() {
                 ui_states.push(UiStates::Settings).unwrap();
             }
-            if main_menu.clicked() {
+            if statistics.clicked() {
+                ui_states.push(UiStates::Statistics).unwrap();
+            }
+            if save_and_quit.clicked() {
+                save_game.save_on_exit = true;
+            }
+            if save_and_quit.clicked() || main_menu.clicked() {
                 game_states.replace(GameStates::MainMenu).unwrap();
                 ui_states.replace(UiStates::MainMenu).unwrap();
             }
@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, ScrollArea, Slider},
+    EguiContext,
+};
+
+use crate::{
+    cfop::Split,
+    persistence,
+    replay::{Replay, ReplayViewer, REPLAYS_DIR},
+    solve_timer::format_duration,
+    statistics::format_date,
+    GameStates,
+};
+
+use super::UiStates;
+
+pub fn show_replays(
+    mut replay_viewer: ResMut<ReplayViewer>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+    mut replays: Local<Option<Vec<Replay>>>,
+) {
+    // replays are read once each time the list is opened, newest first
+    let loaded = replays.get_or_insert_with(|| {
+        persistence::files(REPLAYS_DIR)
+            .iter()
+            .rev()
+            .filter_map(|file_name| Replay::load(file_name))
+            .collect()
+    });
+
+    let mut back = false;
+    egui::Window::new("Replays")
+        .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(300.0);
+
+            if loaded.is_empty() {
+                ui.label("Finished solves are saved as replays.");
+            }
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("replays").striped(true).show(ui, |ui| {
+                    for replay in loaded.iter() {
+                        let size = replay.cube_size;
+                        ui.label(format_date(replay.date));
+                        ui.label(format!("{size}x{size}x{size}"));
+                        ui.label(replay.result.to_string());
+                        if ui.button("Watch").clicked() {
+                            replay_viewer.open(replay.clone());
+                            game_states.push(GameStates::Replay).unwrap();
+                            ui_states.push(UiStates::Replay).unwrap();
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+            if let Some(dir) = persistence::data_dir() {
+                ui.small(format!("Saved in {}", dir.join(REPLAYS_DIR).display()));
+            }
+            back = ui.button("Back").clicked();
+        });
+
+    if back {
+        ui_states.pop().unwrap();
+        *replays = None;
+    }
+}
+
+pub fn show_replay_player(
+    mut replay_viewer: ResMut<ReplayViewer>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Replay")
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -20.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(400.0);
+            if let Some(replay) = &replay_viewer.replay {
+                ui.label(format!("Result: {}", replay.result));
+            }
+            if let Some(splits) = &replay_viewer.splits {
+                ui.collapsing("Splits", |ui| splits_grid(ui, splits));
+            }
+
+            let duration = replay_viewer.duration();
+            let mut position = replay_viewer.position().as_secs_f32();
+            let scrub = ui.add(
+                Slider::new(&mut position, 0.0..=duration.as_secs_f32())
+                    .show_value(false)
+                    .text(format!(
+                        "{} / {}",
+                        format_duration(replay_viewer.position()),
+                        format_duration(duration)
+                    )),
+            );
+            if scrub.changed() {
+                replay_viewer.seek(Duration::from_secs_f32(position));
+            }
+
+            ui.horizontal(|ui| {
+                let play = if replay_viewer.playing {
+                    "Pause"
+                } else {
+                    "Play"
+                };
+                if ui.button(play).clicked() {
+                    // playing again from the end starts over
+                    if !replay_viewer.playing && replay_viewer.position() >= duration {
+                        replay_viewer.seek(Duration::ZERO);
+                    }
+                    replay_viewer.playing = !replay_viewer.playing;
+                }
+                ui.add(
+                    Slider::new(&mut replay_viewer.speed, 0.25..=4.0)
+                        .logarithmic(true)
+                        .text("Speed"),
+                );
+                if ui.button("Back").clicked() {
+                    // replays watched from the results screen have no list to go back to
+                    if game_states.inactives().is_empty() {
+                        game_states.replace(GameStates::MainMenu).unwrap();
+                        ui_states.replace(UiStates::MainMenu).unwrap();
+                    } else {
+                        game_states.pop().unwrap();
+                        ui_states.pop().unwrap();
+                    }
+                }
+            });
+        });
+}
+
+/// Time, moves and TPS of each CFOP stage.
+pub(super) fn splits_grid(ui: &mut egui::Ui, splits: &[Split]) {
+    egui::Grid::new("splits").striped(true).show(ui, |ui| {
+        ui.label("Stage");
+        ui.label("Time");
+        ui.label("Moves");
+        ui.label("TPS");
+        ui.end_row();
+        for split in splits {
+            ui.label(split.stage.to_string());
+            ui.label(format_duration(split.duration));
+            ui.label(split.moves.to_string());
+            ui.label(format!("{:.1}", split.tps()));
+            ui.end_row();
+        }
+    });
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, ComboBox, ScrollArea},
+    EguiContext,
+};
+
+use crate::{
+    notation::format_moves,
+    statistics::{format_date, format_time, Solves},
+};
+
+use super::UiStates;
+
+const RECENT_SOLVES: usize = 50;
+
+pub fn show_statistics(
+    mut solves: ResMut<Solves>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+    mut selected_size: Local<Option<u32>>,
+) {
+    let sizes = solves.cube_sizes();
+    let cube_size = *selected_size.get_or_insert_with(|| sizes.first().copied().unwrap_or(3));
+
+    egui::Window::new("Statistics")
+        .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(320.0);
+
+            ui.horizontal(|ui| {
+                let mut session = solves.session;
+                ComboBox::from_label("Session")
+                    .selected_text(format!("Session {}", session + 1))
+                    .show_ui(ui, |ui| {
+                        for id in solves.sessions() {
+                            ui.selectable_value(&mut session, id, format!("Session {}", id + 1));
+                        }
+                    });
+                if session != solves.session {
+                    solves.session = session;
+                    solves.save();
+                }
+                if ui.button("New session").clicked() {
+                    solves.start_session();
+                    solves.save();
+                }
+            });
+            let selected = selected_size.as_mut().unwrap();
+            ComboBox::from_label("Cube size")
+                .selected_text(format!("{cube_size}x{cube_size}x{cube_size}"))
+                .show_ui(ui, |ui| {
+                    for size in sizes {
+                        ui.selectable_value(selected, size, format!("{size}x{size}x{size}"));
+                    }
+                });
+
+            let stats = solves.stats(cube_size);
+            let bests = solves.personal_bests(cube_size);
+            let time =
+                |time: Option<_>| time.map_or_else(|| "-".to_string(), |t| format_time(Some(t)));
+            let average =
+                |average: Option<Option<_>>| average.map_or_else(|| "-".to_string(), format_time);
+            egui::Grid::new("stats").striped(true).show(ui, |ui| {
+                ui.label("Solves");
+                ui.label(stats.count.to_string());
+                ui.end_row();
+                ui.label("Best");
+                ui.label(time(stats.best));
+                ui.end_row();
+                ui.label("All time best");
+                ui.label(time(bests.single));
+                ui.end_row();
+                ui.label("Worst");
+                ui.label(time(stats.worst));
+                ui.end_row();
+                ui.label("Mean");
+                ui.label(time(stats.mean));
+                ui.end_row();
+
+                ui.label("");
+                ui.label("Current");
+                ui.label("Best");
+                ui.label("All time");
+                ui.end_row();
+                for (stat, all_time) in stats.averages.iter().zip(&bests.averages) {
+                    ui.label(format!("ao{}", stat.n));
+                    ui.label(average(stat.current));
+                    ui.label(average(stat.best));
+                    ui.label(average(*all_time));
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                egui::Grid::new("solves").striped(true).show(ui, |ui| {
+                    for solve in solves.of_size(cube_size).rev().take(RECENT_SOLVES) {
+                        ui.label(format_date(solve.date));
+                        ui.label(solve.result.to_string())
+                            .on_hover_text(format_moves(&solve.scramble));
+                        ui.label(format!("{} moves", solve.moves));
+                        ui.end_row();
+                    }
+                });
+            });
+
+            if ui.button("Back").clicked() {
+                ui_states.pop().unwrap();
+            }
+        });
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Align2, ScrollArea},
+    EguiContext,
+};
+
+use crate::{
+    cases::{CaseKind, Cases},
+    solve_timer::format_duration,
+    trainer::Trainer,
+    GameStates,
+};
+
+use super::UiStates;
+
+const CASES_PER_ROW: usize = 6;
+
+pub fn show_trainer_cases(
+    cases: Res<Cases>,
+    mut trainer: ResMut<Trainer>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    let training = *game_states.current() == GameStates::Trainer;
+    egui::Window::new("Trainer cases")
+        .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(520.0);
+
+            ScrollArea::vertical().max_height(450.0).show(ui, |ui| {
+                for kind in CaseKind::ALL {
+                    ui.horizontal(|ui| {
+                        ui.heading(kind.to_string());
+                        let names = cases.of_kind(kind).map(|(_, case)| case.name.clone());
+                        let all = ui.button("All");
+                        let none = ui.button("None");
+                        if all.clicked() {
+                            trainer.selected.extend(names);
+                        } else if none.clicked() {
+                            for name in names {
+                                trainer.selected.remove(&name);
+                            }
+                        }
+                    });
+                    egui::Grid::new(kind).show(ui, |ui| {
+                        for (i, (_, case)) in cases.of_kind(kind).enumerate() {
+                            let selected = trainer.selected.contains(&case.name);
+                            let label = ui
+                                .selectable_label(selected, &case.name)
+                                .on_hover_text(&case.algorithm);
+                            if label.clicked() && !trainer.selected.remove(&case.name) {
+                                trainer.selected.insert(case.name.clone());
+                            }
+                            if (i + 1) % CASES_PER_ROW == 0 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let start =
+                    ui.add_enabled(!trainer.selected.is_empty(), egui::Button::new("Start"));
+                if start.clicked() {
+                    trainer.save();
+                    // a new selection gets a new case
+                    trainer.attempt = None;
+                    if training {
+                        ui_states.replace(UiStates::Trainer).unwrap();
+                    } else {
+                        game_states.push(GameStates::Trainer).unwrap();
+                        ui_states.replace(UiStates::Trainer).unwrap();
+                    }
+                }
+                if ui.button("Back").clicked() {
+                    trainer.save();
+                    if training {
+                        ui_states.replace(UiStates::Trainer).unwrap();
+                    } else {
+                        ui_states.pop().unwrap();
+                    }
+                }
+            });
+        });
+}
+
+pub fn show_trainer(
+    mut trainer: ResMut<Trainer>,
+    mut game_states: ResMut<State<GameStates>>,
+    mut ui_states: ResMut<State<UiStates>>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    egui::Window::new("Trainer")
+        .anchor(Align2::CENTER_TOP, (0.0, 20.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.set_width(260.0);
+
+            let time = trainer.attempt.as_ref().and_then(|attempt| attempt.time);
+            ui.heading(time.map_or_else(|| "Ready".to_string(), format_duration));
+            if let Some((case, time)) = &trainer.previous {
+                let result = time.map_or_else(|| "skipped".to_string(), format_duration);
+                ui.label(format!("{}: {result}", case.name));
+                ui.small(&case.algorithm);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Skip").clicked() {
+                    trainer.finish(None);
+                    trainer.save();
+                }
+                if ui.button("Cases").clicked() {
+                    ui_states.replace(UiStates::TrainerCases).unwrap();
+                }
+                if ui.button("Main menu").clicked() {
+                    game_states.pop().unwrap();
+                    ui_states.pop().unwrap();
+                }
+            });
+
+            ui.collapsing("Statistics", |ui| {
+                egui::Grid::new("trainer stats")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Case");
+                        ui.label("Accuracy");
+                        ui.label("Average");
+                        ui.end_row();
+                        for name in trainer.selected.iter() {
+                            let Some(stats) = trainer.stats.get(name) else {
+                                continue;
+                            };
+                            ui.label(name);
+                            ui.label(stats.accuracy().map_or_else(
+                                || "-".to_string(),
+                                |accuracy| {
+                                    format!("{:.0}% of {}", accuracy * 100.0, stats.attempts)
+                                },
+                            ));
+                            ui.label(
+                                stats
+                                    .average()
+                                    .map_or_else(|| "-".to_string(), format_duration),
+                            );
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+}
@@ -0,0 +1,259 @@
+use std::{fmt::Display, fs, path::Path, time::Duration};
+
+use crate::{
+    notation::moves_layer_turns,
+    replay::Replay,
+    rubiks_cube::RubiksCube,
+    rubiks_cube_plugin::{MAX_CUBE_SIDES, MIN_CUBE_SIDES},
+    solve_timer::Penalty,
+};
+
+/// Recorded times and the reported result are measured in different frames,
+/// so they may differ by a few frames.
+const TIME_TOLERANCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    Io(String),
+    // also returned for truncated files
+    Parse(String),
+    InvalidCubeSize(u32),
+    InvalidTurn {
+        index: usize,
+    },
+    TimestampsNotMonotonic {
+        index: usize,
+    },
+    // DNF results have no time to verify
+    Dnf,
+    // the scramble leaves the cube solved, so there is nothing to time
+    NotScrambled,
+    // only whole cube rotations, the timer never starts
+    NoTurns,
+    NotSolved,
+    PenaltyMismatch {
+        reported: Penalty,
+        recorded: Penalty,
+    },
+    TimeMismatch {
+        reported: Duration,
+        recorded: Duration,
+    },
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::Io(e) => write!(f, "Could not read the replay: {e}"),
+            VerifyError::Parse(e) => write!(f, "Could not parse the replay: {e}"),
+            VerifyError::InvalidCubeSize(size) => write!(f, "Invalid cube size {size}"),
+            VerifyError::InvalidTurn { index } => write!(f, "Turn {index} is not on the cube"),
+            VerifyError::TimestampsNotMonotonic { index } => {
+                write!(f, "Turn {index} was made before the previous one")
+            }
+            VerifyError::Dnf => write!(f, "The solve is a DNF"),
+            VerifyError::NotScrambled => write!(f, "The scramble does not scramble the cube"),
+            VerifyError::NoTurns => write!(f, "The replay has no turns that start the timer"),
+            VerifyError::NotSolved => write!(f, "The turns do not solve the scramble"),
+            VerifyError::PenaltyMismatch { reported, recorded } => write!(
+                f,
+                "Reported penalty {reported:?} does not match the inspection ({recorded:?})"
+            ),
+            VerifyError::TimeMismatch { reported, recorded } => write!(
+                f,
+                "Reported time {:.3}s does not match the recorded {:.3}s",
+                reported.as_secs_f32(),
+                recorded.as_secs_f32()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Loads a replay file and verifies it.
+pub fn verify_file(path: &Path) -> Result<Replay, VerifyError> {
+    let content = fs::read_to_string(path).map_err(|e| VerifyError::Io(e.to_string()))?;
+    let replay: Replay = ron::from_str(&content).map_err(|e| VerifyError::Parse(e.to_string()))?;
+    verify(&replay)?;
+    Ok(replay)
+}
+
+/// Checks that the turns of the replay solve its scramble applied to a solved
+/// cube and that the reported result matches the recorded timestamps.
+pub fn verify(replay: &Replay) -> Result<(), VerifyError> {
+    let side_size = replay.cube_size;
+    if !(MIN_CUBE_SIDES..=MAX_CUBE_SIDES).contains(&side_size) {
+        return Err(VerifyError::InvalidCubeSize(side_size));
+    }
+    if replay.result.penalty == Penalty::Dnf {
+        return Err(VerifyError::Dnf);
+    }
+
+    let mut rb = RubiksCube::new(side_size);
+    for turn in moves_layer_turns(&replay.scramble, side_size) {
+        rb.turn(&turn);
+    }
+    if rb.is_solved() {
+        return Err(VerifyError::NotScrambled);
+    }
+    // the timer starts with the first turn that is not a rotation
+    // and stops with the last one
+    let start = replay
+        .turns
+        .iter()
+        .find(|timed| !timed.turn.is_rotation(side_size))
+        .ok_or(VerifyError::NoTurns)?
+        .time;
+
+    for (index, timed) in replay.turns.iter().enumerate() {
+        let turn = timed.turn;
+        if turn.width == 0 || turn.layer + turn.width > side_size {
+            return Err(VerifyError::InvalidTurn { index });
+        }
+        if index > 0 && timed.time < replay.turns[index - 1].time {
+            return Err(VerifyError::TimestampsNotMonotonic { index });
+        }
+        rb.turn(&turn);
+    }
+    if !rb.is_solved() {
+        return Err(VerifyError::NotSolved);
+    }
+
+    let end = replay.duration();
+
+    let reported = replay.result.penalty;
+    let earliest = Penalty::after_inspection(start.saturating_sub(TIME_TOLERANCE));
+    let latest = Penalty::after_inspection(start + TIME_TOLERANCE);
+    if reported != earliest && reported != latest {
+        return Err(VerifyError::PenaltyMismatch {
+            reported,
+            recorded: Penalty::after_inspection(start),
+        });
+    }
+
+    let recorded = end - start;
+    let reported = replay.result.time;
+    if reported.max(recorded) - reported.min(recorded) > TIME_TOLERANCE {
+        return Err(VerifyError::TimeMismatch { reported, recorded });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        notation::parse_moves,
+        replay::TimedTurn,
+        rubiks_cube::{Axis, LayerTurn, Rotation},
+        solve_timer::SolveResult,
+    };
+
+    fn secs(s: f32) -> Duration {
+        Duration::from_secs_f32(s)
+    }
+
+    // scramble R U, inspected with a y y' and solved with U' R'
+    fn replay() -> Replay {
+        let turns = moves_layer_turns(&parse_moves("y y' U' R'").unwrap(), 3);
+        let times = [secs(2.0), secs(3.0), secs(10.0), secs(12.5)];
+        Replay {
+            cube_size: 3,
+            scramble: parse_moves("R U").unwrap(),
+            turns: turns
+                .into_iter()
+                .zip(times)
+                .map(|(turn, time)| TimedTurn { time, turn })
+                .collect(),
+            result: SolveResult {
+                time: secs(2.5),
+                penalty: Penalty::None,
+            },
+            date: 0,
+        }
+    }
+
+    #[test]
+    fn verify_valid() {
+        assert_eq!(verify(&replay()), Ok(()));
+
+        let mut replay = replay();
+        replay.turns[2].time = secs(15.5);
+        replay.turns[3].time = secs(18.0);
+        replay.result.penalty = Penalty::PlusTwo;
+        assert_eq!(verify(&replay), Ok(()));
+    }
+
+    #[test]
+    fn verify_tampered() {
+        let mut tampered = replay();
+        tampered.result.time = secs(1.5);
+        assert!(matches!(
+            verify(&tampered),
+            Err(VerifyError::TimeMismatch { .. })
+        ));
+
+        let mut tampered = replay();
+        tampered.turns[2].time = secs(16.0);
+        tampered.turns[3].time = secs(18.5);
+        assert!(matches!(
+            verify(&tampered),
+            Err(VerifyError::PenaltyMismatch { .. })
+        ));
+
+        let mut tampered = replay();
+        tampered.turns[1].time = secs(1.0);
+        assert_eq!(
+            verify(&tampered),
+            Err(VerifyError::TimestampsNotMonotonic { index: 1 })
+        );
+
+        let mut tampered = replay();
+        tampered.turns.pop();
+        assert_eq!(verify(&tampered), Err(VerifyError::NotSolved));
+
+        let mut tampered = replay();
+        tampered.turns[0].turn = LayerTurn {
+            axis: Axis::X,
+            layer: 2,
+            width: 2,
+            rotation: Rotation::Cw,
+        };
+        assert_eq!(
+            verify(&tampered),
+            Err(VerifyError::InvalidTurn { index: 0 })
+        );
+
+        // nothing to solve
+        let mut tampered = replay();
+        tampered.scramble.clear();
+        tampered.turns.clear();
+        tampered.result.time = Duration::ZERO;
+        assert_eq!(verify(&tampered), Err(VerifyError::NotScrambled));
+        tampered.scramble = parse_moves("y").unwrap();
+        assert_eq!(verify(&tampered), Err(VerifyError::NotScrambled));
+
+        let mut tampered = replay();
+        tampered.turns.truncate(2);
+        assert_eq!(verify(&tampered), Err(VerifyError::NoTurns));
+
+        let mut tampered = replay();
+        tampered.cube_size = 1;
+        assert_eq!(verify(&tampered), Err(VerifyError::InvalidCubeSize(1)));
+    }
+
+    #[test]
+    fn verify_truncated_file() {
+        let path = std::env::temp_dir().join("rubiks_cube_truncated_replay.ron");
+        let content = ron::to_string(&replay()).unwrap();
+        fs::write(&path, &content[..content.len() / 2]).unwrap();
+        assert!(matches!(verify_file(&path), Err(VerifyError::Parse(_))));
+
+        fs::write(&path, &content).unwrap();
+        assert_eq!(verify_file(&path), Ok(replay()));
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(verify_file(&path), Err(VerifyError::Io(_))));
+    }
+}